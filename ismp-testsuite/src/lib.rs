@@ -16,24 +16,49 @@
 //! ISMP Testsuite
 
 pub mod mocks;
+pub mod snapshot;
 #[cfg(test)]
 mod tests;
 
-use crate::mocks::MOCK_CONSENSUS_CLIENT_ID;
+use crate::{
+    mocks::{
+        Host, MockClock, MockModule, MOCK_BYZANTINE_PROOF, MOCK_CONSENSUS_CLIENT_ID,
+        MOCK_CONSENSUS_PEER, MOCK_GOVERNANCE_AUTHORITY, MOCK_PRUNED_STATE, MOCK_REJECTING_MODULE,
+        MOCK_ROLLBACK_PROOF, MOCK_STALE_PROOF, MOCK_TIMEOUT_GAS, MOCK_UNKNOWN_MODULE,
+        MOCK_VERSIONED_PROOF,
+    },
+    snapshot::{Mutation, SnapshotHost},
+};
+use codec::{Decode, Encode};
 use ismp::{
     consensus::{
-        ConsensusStateId, IntermediateState, StateCommitment, StateMachineHeight, StateMachineId,
+        ConsensusStateId, IntermediateState, ProofMetadata, StateCommitment, StateMachineHeight,
+        StateMachineId, StateProofResult, VerifiedCommitments,
     },
-    handlers::handle_incoming_message,
-    host::{Ethereum, IsmpHost, StateMachine},
+    error::Error,
+    events::{DeliveryOutcome, TraceStage},
+    handlers::{
+        acknowledge_finalized_commitments, create_client, dry_run_update_client,
+        handle_incoming_message, handle_incoming_messages, promote_pending_update,
+        record_consensus_ack, set_unbonding_period, unfreeze_state_machine, MessageResult,
+    },
+    host::{Ethereum, FreezeReason, IsmpHost, RequestStatus, StateMachine, UnknownPolicy},
     messaging::{
-        ConsensusMessage, Message, Proof, RequestMessage, ResponseMessage, TimeoutMessage,
+        ConsensusAckMessage, ConsensusMessage, CreateConsensusState, FraudProofMessage, Message,
+        Proof, RequestMessage, ResponseMessage, SetUnbondingPeriodMessage, StateCommitmentHeight,
+        TimeoutMessage, UnfreezeStateMachineMessage,
     },
+    module::{DispatchResultExt, DispatchSuccess, ModuleRegistry},
     router::{
-        DispatchPost, DispatchRequest, IsmpDispatcher, Post, PostResponse, Request, Response,
+        DispatchGet, DispatchPost, DispatchRequest, Get, GetResponse, IsmpDispatcher, Post,
+        PostResponse, Request, Response, MAX_POST_META_LEN, REQUEST_VERSION,
     },
-    util::hash_request,
+    util::{hash_request, request_receipt_key},
+    verify::{verify_get_response, verify_request_membership},
+    version::ISMP_PROTOCOL_VERSION,
 };
+use primitive_types::H256;
+use std::{rc::Rc, time::Duration};
 
 fn mock_consensus_state_id() -> ConsensusStateId {
     *b"mock"
@@ -59,19 +84,109 @@ fn setup_mock_client<H: IsmpHost>(host: &H) -> IntermediateState {
     host.store_consensus_state_id(mock_consensus_state_id(), MOCK_CONSENSUS_CLIENT_ID).unwrap();
     host.store_state_machine_commitment(intermediate_state.height, intermediate_state.commitment)
         .unwrap();
+    host.store_latest_commitment_height(intermediate_state.height).unwrap();
+
+    intermediate_state
+}
+
+/// Set up the mock client, updated at the current time, and advance the clock past its challenge
+/// period so that messages proven against it are accepted. The host must read its timestamp from
+/// `clock`.
+fn setup_aged_client<H: IsmpHost>(host: &H, clock: &MockClock) -> IntermediateState {
+    let intermediate_state = setup_mock_client(host);
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    host.store_consensus_update_time(mock_consensus_state_id(), host.timestamp()).unwrap();
+    host.store_state_machine_update_time(intermediate_state.height, host.timestamp()).unwrap();
+    clock.advance(challenge_period * 2);
 
     intermediate_state
 }
+
+/// A POST request between two state machines with placeholder module ids and data
+fn mock_post(source: StateMachine, dest: StateMachine, nonce: u64) -> Post {
+    Post {
+        source,
+        dest,
+        nonce,
+        from: vec![0u8; 32],
+        to: vec![0u8; 32],
+        timeout_timestamp: 0,
+        data: vec![0u8; 64],
+        gas_limit: 0,
+        deliver_by: None,
+        version: REQUEST_VERSION,
+        response_to: None,
+        meta: None,
+        expects_response: true,
+    }
+}
+
+/// The dispatch parameters of a [`mock_post`] sent by the host
+fn mock_dispatch_post(dest: StateMachine) -> DispatchPost {
+    DispatchPost {
+        dest,
+        from: vec![0u8; 32],
+        to: vec![0u8; 32],
+        timeout_timestamp: 0,
+        data: vec![0u8; 64],
+        gas_limit: 0,
+        deliver_by: None,
+        response_to: None,
+        meta: None,
+        expects_response: true,
+    }
+}
+
+/// A GET request sent by the host, reading the given keys at the given height of `dest`
+fn mock_get<H: IsmpHost>(
+    host: &H,
+    dest: StateMachine,
+    nonce: u64,
+    keys: Vec<Vec<u8>>,
+    height: u64,
+) -> Get {
+    Get {
+        source: host.host_state_machine(),
+        dest,
+        nonce,
+        from: vec![0u8; 32],
+        keys,
+        height,
+        timeout_timestamp: 0,
+        gas_limit: 0,
+    }
+}
+
+/// A consensus message for the mock client, see [`mocks::MockClient`] for how the proof is read
+fn mock_consensus_message(consensus_proof: Vec<u8>) -> Message {
+    Message::Consensus(ConsensusMessage {
+        consensus_proof,
+        consensus_state_id: mock_consensus_state_id(),
+        expected_trusted_state: None,
+        protocol_version: None,
+    })
+}
+
+/// A mock consensus proof finalizing the given commitments of a single state machine
+fn mock_consensus_proof(
+    state_id: StateMachine,
+    commitments: Vec<StateCommitmentHeight>,
+) -> Vec<u8> {
+    VerifiedCommitments::from([(state_id, commitments)]).encode()
+}
+
+/// A request message carrying the given requests, proven at the given height
+fn mock_request_message(requests: Vec<Post>, height: StateMachineHeight) -> Message {
+    Message::Request(RequestMessage { requests, proof: Proof { height, proof: vec![] } })
+}
+
 /*
     Consensus Client and State Machine checks
 */
 
 /// Ensure challenge period rules are followed in all handlers
 pub fn check_challenge_period<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
-    let consensus_message = Message::Consensus(ConsensusMessage {
-        consensus_proof: vec![],
-        consensus_state_id: mock_consensus_state_id(),
-    });
+    let consensus_message = mock_consensus_message(vec![]);
     let intermediate_state = setup_mock_client(host);
     // Set the previous update time
     let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
@@ -81,22 +196,11 @@ pub fn check_challenge_period<H: IsmpHost>(host: &H) -> Result<(), &'static str>
     let res = handle_incoming_message::<H>(host, consensus_message);
     assert!(matches!(res, Err(ismp::error::Error::ChallengePeriodNotElapsed { .. })));
 
-    let post = Post {
-        source: host.host_state_machine(),
-        dest: StateMachine::Kusama(2000),
-        nonce: 0,
-        from: vec![0u8; 32],
-        to: vec![0u8; 32],
-        timeout_timestamp: 0,
-        data: vec![0u8; 64],
-        gas_limit: 0,
-    };
+    let post = mock_post(host.host_state_machine(), StateMachine::Kusama(2000), 0);
     let request = Request::Post(post.clone());
-    // Request message handling check
-    let request_message = Message::Request(RequestMessage {
-        requests: vec![post.clone()],
-        proof: Proof { height: intermediate_state.height, proof: vec![] },
-    });
+    // Request message handling check, for an incoming request addressed to the host
+    let incoming = Post { source: post.dest, dest: post.source, ..post.clone() };
+    let request_message = mock_request_message(vec![incoming], intermediate_state.height);
 
     let res = handle_incoming_message(host, request_message);
 
@@ -104,8 +208,9 @@ pub fn check_challenge_period<H: IsmpHost>(host: &H) -> Result<(), &'static str>
 
     // Response message handling check
     let response_message = Message::Response(ResponseMessage::Post {
-        responses: vec![Response::Post(PostResponse { post, response: vec![] })],
+        responses: vec![Response::Post(post.respond(vec![]))],
         proof: Proof { height: intermediate_state.height, proof: vec![] },
+        receipt_proof: None,
     });
 
     let res = handle_incoming_message(host, response_message);
@@ -122,183 +227,1902 @@ pub fn check_challenge_period<H: IsmpHost>(host: &H) -> Result<(), &'static str>
     Ok(())
 }
 
-/// Ensure expired client rules are followed in consensus update
-pub fn check_client_expiry<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
-    let consensus_message = Message::Consensus(ConsensusMessage {
-        consensus_proof: vec![],
+/// Ensure a consensus update is rejected while the previous update is within its challenge period
+pub fn check_consecutive_updates<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let consensus_message = |height| {
+        let commitment = StateCommitmentHeight {
+            commitment: StateCommitment {
+                timestamp: 1000 + height,
+                overlay_root: None,
+                state_root: Default::default(),
+            },
+            height,
+        };
+        mock_consensus_message(mock_consensus_proof(
+            intermediate_state.height.id.state_id,
+            vec![commitment],
+        ))
+    };
+
+    handle_incoming_message(host, consensus_message(2))
+        .map_err(|_| "Failed to process consensus message")?;
+    let update_time = host.consensus_update_time(mock_consensus_state_id()).unwrap();
+
+    let res = handle_incoming_message(host, consensus_message(3));
+    assert!(matches!(res, Err(ismp::error::Error::ChallengePeriodNotElapsed { .. })));
+    // The rejected update must not affect the pending challenge period
+    assert_eq!(host.consensus_update_time(mock_consensus_state_id()).unwrap(), update_time);
+
+    Ok(())
+}
+
+/// Ensure a successful update restarts the challenge period, so that resubmitting the same
+/// consensus message straight away is rejected.
+pub fn check_no_replay_after_update<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let previous_update_time = host.consensus_update_time(mock_consensus_state_id()).unwrap();
+    let commitment = StateCommitmentHeight {
+        commitment: intermediate_state.commitment,
+        height: intermediate_state.height.height + 1,
+    };
+    let consensus_message = mock_consensus_message(mock_consensus_proof(
+        intermediate_state.height.id.state_id,
+        vec![commitment],
+    ));
+
+    handle_incoming_message(host, consensus_message.clone())
+        .map_err(|_| "Failed to process consensus message")?;
+    let update_time = host.consensus_update_time(mock_consensus_state_id()).unwrap();
+    assert!(update_time > previous_update_time);
+
+    let res = handle_incoming_message(host, consensus_message);
+    assert!(matches!(
+        res,
+        Err(ismp::error::Error::ChallengePeriodNotElapsed { .. } |
+            ismp::error::Error::DuplicateConsensusProof { .. })
+    ));
+    assert_eq!(host.consensus_update_time(mock_consensus_state_id()).unwrap(), update_time);
+
+    Ok(())
+}
+
+/// A well formed message creating the mock consensus state
+fn mock_create_client_message() -> CreateConsensusState {
+    let id = StateMachineId {
+        state_id: StateMachine::Ethereum(Ethereum::ExecutionLayer),
         consensus_state_id: mock_consensus_state_id(),
-    });
+    };
+    let commitment =
+        StateCommitment { timestamp: 1000, overlay_root: None, state_root: [1u8; 32].into() };
+    CreateConsensusState {
+        consensus_state: vec![1u8],
+        consensus_client_id: MOCK_CONSENSUS_CLIENT_ID,
+        consensus_state_id: mock_consensus_state_id(),
+        unbonding_period: 1_000_000,
+        challenge_period: 0,
+        state_machine_commitments: vec![(id, StateCommitmentHeight { commitment, height: 1 })],
+    }
+}
+
+/// Ensure a consensus client cannot be created under a consensus state id that is already in use
+pub fn duplicate_consensus_state_id_check<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
     setup_mock_client(host);
-    // Set the previous update time
-    let unbonding_period = host.unbonding_period(mock_consensus_state_id()).unwrap();
-    let previous_update_time = host.timestamp() - unbonding_period;
-    host.store_consensus_update_time(mock_consensus_state_id(), previous_update_time).unwrap();
+    let existing = host.consensus_state(mock_consensus_state_id()).unwrap();
 
-    let res = handle_incoming_message::<H>(host, consensus_message);
-    assert!(matches!(res, Err(ismp::error::Error::UnbondingPeriodElapsed { .. })));
+    let res = create_client(host, mock_create_client_message());
+    assert!(matches!(res, Err(ismp::error::Error::DuplicateConsensusStateId { .. })));
+    // The existing consensus state must not be overwritten
+    assert_eq!(host.consensus_state(mock_consensus_state_id()).unwrap(), existing);
 
     Ok(())
 }
 
-/// Frozen state machine checks in message handlers
-pub fn frozen_check<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
+/// Ensure consensus clients aren't created from malformed messages, even if they were built
+/// without [`CreateConsensusState::new`]
+pub fn malformed_create_client_check<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
+    let message = mock_create_client_message();
+    let (id, commitment) = message.state_machine_commitments[0].clone();
+    let with_commitments = |state_machine_commitments| CreateConsensusState {
+        state_machine_commitments,
+        ..message.clone()
+    };
+
+    let zero_height = StateCommitmentHeight { height: 0, ..commitment.clone() };
+    let res = create_client(host, with_commitments(vec![(id, zero_height)]));
+    assert!(matches!(res, Err(Error::ZeroCommitmentHeight { .. })));
+    let res = create_client(host, with_commitments(vec![(id, commitment.clone()); 2]));
+    assert!(matches!(res, Err(Error::DuplicateStateCommitment { .. })));
+    let zero_root = StateCommitment { state_root: Default::default(), ..commitment.commitment };
+    let res = create_client(
+        host,
+        with_commitments(vec![(id, StateCommitmentHeight { commitment: zero_root, height: 1 })]),
+    );
+    assert!(matches!(res, Err(Error::ZeroStateRoot { .. })));
+    assert!(host.consensus_client_id(mock_consensus_state_id()).is_none());
+
+    // Clients may start from a consensus state alone, their commitments arrive with updates
+    create_client(host, with_commitments(vec![]))
+        .map_err(|_| "Failed to create client without commitments")?;
+    assert_eq!(host.consensus_client_id(mock_consensus_state_id()), Some(MOCK_CONSENSUS_CLIENT_ID));
+
+    Ok(())
+}
+
+/// Ensure consensus clients can't be created for state machines conventionally tracked by a
+/// different client type
+pub fn unexpected_client_type_check<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
     let intermediate_state = setup_mock_client(host);
-    // Set the previous update time
-    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
-    let previous_update_time = host.timestamp() - (challenge_period * 2);
-    host.store_consensus_update_time(mock_consensus_state_id(), previous_update_time).unwrap();
-    host.store_state_machine_update_time(intermediate_state.height, previous_update_time).unwrap();
-    let frozen_height = StateMachineHeight {
-        id: intermediate_state.height.id,
-        height: intermediate_state.height.height - 1,
+    let state_machine = intermediate_state.height.id.state_id;
+    let message = CreateConsensusState {
+        consensus_state: vec![],
+        consensus_client_id: MOCK_CONSENSUS_CLIENT_ID,
+        consensus_state_id: *b"eth0",
+        unbonding_period: 1_000_000,
+        challenge_period: 0,
+        state_machine_commitments: vec![(
+            StateMachineId { state_id: state_machine, consensus_state_id: *b"eth0" },
+            StateCommitmentHeight {
+                commitment: StateCommitment {
+                    state_root: [1u8; 32].into(),
+                    ..intermediate_state.commitment
+                },
+                height: 1,
+            },
+        )],
     };
-    host.freeze_state_machine(frozen_height).unwrap();
 
-    let post = Post {
-        source: host.host_state_machine(),
-        dest: StateMachine::Kusama(2000),
-        nonce: 0,
-        from: vec![0u8; 32],
-        to: vec![0u8; 32],
-        timeout_timestamp: 0,
-        data: vec![0u8; 64],
-        gas_limit: 0,
+    let res = create_client(host, message.clone());
+    assert!(matches!(
+        res,
+        Err(Error::UnexpectedClientType { state_machine: sm, expected, got })
+            if sm == state_machine &&
+                expected == state_machine.default_consensus_client() &&
+                got == MOCK_CONSENSUS_CLIENT_ID
+    ));
+    assert!(host.consensus_client_id(*b"eth0").is_none());
+
+    // Parachains have no single conventional client, so any client type may track them
+    let parachain =
+        StateMachineId { state_id: StateMachine::Kusama(2000), consensus_state_id: *b"ksm0" };
+    let message = CreateConsensusState {
+        consensus_state_id: *b"ksm0",
+        state_machine_commitments: vec![(
+            parachain,
+            message.state_machine_commitments[0].1.clone(),
+        )],
+        ..message
     };
-    let request = Request::Post(post.clone());
-    // Request message handling check
-    let request_message = Message::Request(RequestMessage {
-        requests: vec![post.clone()],
-        proof: Proof { height: intermediate_state.height, proof: vec![] },
-    });
+    create_client(host, message).map_err(|_| "Failed to create parachain client")?;
+    assert_eq!(host.consensus_client_id(*b"ksm0"), Some(MOCK_CONSENSUS_CLIENT_ID));
 
-    let res = handle_incoming_message(host, request_message);
+    Ok(())
+}
 
-    assert!(matches!(res, Err(ismp::error::Error::FrozenStateMachine { .. })));
+/// Ensure consensus updates carrying decreasing commitment timestamps for a state machine are
+/// rejected
+pub fn commitment_timestamp_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let commitment = |height, timestamp| StateCommitmentHeight {
+        commitment: StateCommitment {
+            timestamp,
+            overlay_root: None,
+            state_root: Default::default(),
+        },
+        height,
+    };
+    let commitments = vec![commitment(2, 1005), commitment(3, 1004)];
+    let consensus_message = mock_consensus_message(mock_consensus_proof(
+        intermediate_state.height.id.state_id,
+        commitments,
+    ));
+    let res = handle_incoming_message(host, consensus_message);
+    assert!(matches!(res, Err(ismp::error::Error::NonMonotonicCommitmentTimestamp { .. })));
+    let height = StateMachineHeight { id: intermediate_state.height.id, height: 2 };
+    assert!(host.state_machine_commitment(height).is_err());
 
-    // Response message handling check
-    let response_message = Message::Response(ResponseMessage::Post {
-        responses: vec![Response::Post(PostResponse { post, response: vec![] })],
-        proof: Proof { height: intermediate_state.height, proof: vec![] },
-    });
+    Ok(())
+}
 
-    let res = handle_incoming_message(host, response_message);
-    assert!(matches!(res, Err(ismp::error::Error::FrozenStateMachine { .. })));
+/// Ensure consensus updates are accepted for state machines without a stored commitment height
+pub fn first_commitment_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    setup_aged_client(host, clock);
+    let id = StateMachineId {
+        state_id: StateMachine::Kusama(2000),
+        consensus_state_id: mock_consensus_state_id(),
+    };
+    let res = host.latest_commitment_height(id);
+    assert!(matches!(res, Err(ismp::error::Error::NoCommitmentHeight { .. })));
 
-    // Timeout mesaage handling check
-    let timeout_message = Message::Timeout(TimeoutMessage::Post {
-        requests: vec![request],
-        timeout_proof: Proof { height: intermediate_state.height, proof: vec![] },
-    });
+    let commitment = StateCommitmentHeight {
+        commitment: StateCommitment {
+            timestamp: 1000,
+            overlay_root: None,
+            state_root: Default::default(),
+        },
+        height: 5,
+    };
+    let consensus_message =
+        mock_consensus_message(mock_consensus_proof(id.state_id, vec![commitment]));
+    let res = handle_incoming_message(host, consensus_message)
+        .map_err(|_| "Failed to process consensus message")?;
+    let MessageResult::ConsensusMessage(result) = res else {
+        return Err("Expected consensus update result")
+    };
+    assert!(result.advanced_machines().contains(&id));
+    assert_eq!(host.latest_commitment_height(id).unwrap(), 5);
+    host.state_machine_commitment(StateMachineHeight { id, height: 5 })
+        .map_err(|_| "Expected commitment to be stored")?;
 
-    let res = handle_incoming_message(host, timeout_message);
-    assert!(matches!(res, Err(ismp::error::Error::FrozenStateMachine { .. })));
+    Ok(())
+}
+
+/// Ensure consensus updates that only change the consensus state, e.g. an authority set
+/// rotation, are accepted
+pub fn housekeeping_update_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    setup_aged_client(host, clock);
+    let previous_update_time = host.consensus_update_time(mock_consensus_state_id()).unwrap();
+
+    let consensus_message = mock_consensus_message(VerifiedCommitments::new().encode());
+    let res = handle_incoming_message(host, consensus_message)
+        .map_err(|_| "Failed to process consensus message")?;
+    let MessageResult::ConsensusMessage(result) = res else {
+        return Err("Expected consensus update result")
+    };
+    assert!(result.is_housekeeping());
+    assert!(result.new_commitments().is_empty());
+    assert!(host.consensus_update_time(mock_consensus_state_id()).unwrap() > previous_update_time);
 
     Ok(())
 }
 
-/// Ensure all timeout post processing is correctly done.
-pub fn timeout_post_processing_check<H: IsmpHost>(
+/// Ensure consensus updates are accepted once the challenge period of the previous update has
+/// elapsed
+pub fn challenge_period_elapsed_check<H: IsmpHost>(
     host: &H,
-    dispatcher: &dyn IsmpDispatcher,
+    clock: &MockClock,
 ) -> Result<(), &'static str> {
-    let intermediate_state = setup_mock_client(host);
+    setup_mock_client(host);
     let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
-    let previous_update_time = host.timestamp() - (challenge_period * 2);
-    host.store_consensus_update_time(mock_consensus_state_id(), previous_update_time).unwrap();
-    host.store_state_machine_update_time(intermediate_state.height, previous_update_time).unwrap();
-    let dispatch_post = DispatchPost {
-        dest: StateMachine::Kusama(2000),
-        from: vec![0u8; 32],
-        to: vec![0u8; 32],
-        timeout_timestamp: intermediate_state.commitment.timestamp,
-        data: vec![0u8; 64],
-        gas_limit: 0,
+    host.store_consensus_update_time(mock_consensus_state_id(), host.timestamp()).unwrap();
+    let consensus_message = mock_consensus_message(VerifiedCommitments::new().encode());
+
+    let res = handle_incoming_message(host, consensus_message.clone());
+    assert!(matches!(res, Err(Error::ChallengePeriodNotElapsed { .. })));
+
+    clock.advance(challenge_period + Duration::from_secs(1));
+    handle_incoming_message(host, consensus_message)
+        .map_err(|_| "Failed to process consensus message")?;
+    assert_eq!(host.consensus_update_time(mock_consensus_state_id()).unwrap(), host.timestamp());
+
+    Ok(())
+}
+
+/// Ensure consensus updates that don't advance the consensus height are rejected
+pub fn consensus_not_advanced_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    setup_aged_client(host, clock);
+    host.store_consensus_state(mock_consensus_state_id(), 5u64.encode()).unwrap();
+    let previous_update_time = host.consensus_update_time(mock_consensus_state_id()).unwrap();
+
+    let res = handle_incoming_message(host, mock_consensus_message(MOCK_STALE_PROOF.to_vec()));
+    assert!(matches!(
+        res,
+        Err(Error::ConsensusNotAdvanced { trusted_height: 5, new_height: 5, .. })
+    ));
+    assert_eq!(
+        host.consensus_update_time(mock_consensus_state_id()).unwrap(),
+        previous_update_time
+    );
+
+    Ok(())
+}
+
+/// Ensure messages declaring an incompatible protocol version are rejected
+pub fn protocol_version_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    setup_aged_client(host, clock);
+    let consensus_message = |protocol_version| {
+        Message::Consensus(ConsensusMessage {
+            consensus_proof: VerifiedCommitments::new().encode(),
+            consensus_state_id: mock_consensus_state_id(),
+            expected_trusted_state: None,
+            protocol_version: Some(protocol_version),
+        })
     };
-    let post = Post {
-        source: host.host_state_machine(),
-        dest: StateMachine::Kusama(2000),
-        nonce: 0,
-        from: vec![0u8; 32],
-        to: vec![0u8; 32],
-        timeout_timestamp: intermediate_state.commitment.timestamp,
-        data: vec![0u8; 64],
-        gas_limit: 0,
+    let res = handle_incoming_message(host, consensus_message(ISMP_PROTOCOL_VERSION + 0x0100));
+    assert!(matches!(res, Err(Error::IncompatibleProtocolVersion { .. })));
+
+    handle_incoming_message(host, consensus_message(ISMP_PROTOCOL_VERSION + 1))
+        .map_err(|_| "Compatible protocol versions should be accepted")?;
+
+    Ok(())
+}
+
+/// Ensure a shortened unbonding period only applies from the next consensus update, so that it
+/// cannot instantly expire a client that is still within its current unbonding window.
+pub fn unbonding_period_rotation_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    setup_aged_client(host, clock);
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    let previous_period = host.unbonding_period(mock_consensus_state_id()).unwrap();
+
+    // Unauthorized accounts cannot change the unbonding period
+    let message = SetUnbondingPeriodMessage {
+        consensus_state_id: mock_consensus_state_id(),
+        new_period: challenge_period.as_secs(),
     };
-    let request = Request::Post(post);
-    let dispatch_request = DispatchRequest::Post(dispatch_post);
-    dispatcher.dispatch_request(dispatch_request).unwrap();
+    let res = set_unbonding_period(host, &[0u8; 32], message.clone());
+    assert!(matches!(res, Err(ismp::error::Error::Unauthorized { .. })));
+    assert_eq!(host.pending_unbonding_period(mock_consensus_state_id()), None);
 
-    // Timeout message handling check
-    let timeout_message = Message::Timeout(TimeoutMessage::Post {
-        requests: vec![request.clone()],
-        timeout_proof: Proof { height: intermediate_state.height, proof: vec![] },
-    });
+    // The new period is shorter than the time elapsed since the last update
+    set_unbonding_period(host, &MOCK_GOVERNANCE_AUTHORITY, message)
+        .map_err(|_| "Governance authority failed to set the unbonding period")?;
+    assert_eq!(host.unbonding_period(mock_consensus_state_id()), Some(previous_period));
+    host.is_expired(mock_consensus_state_id())
+        .map_err(|_| "Shortening the unbonding period should not expire the client")?;
 
-    handle_incoming_message(host, timeout_message).unwrap();
+    handle_incoming_message(host, mock_consensus_message(VerifiedCommitments::new().encode()))
+        .map_err(|_| "Failed to process consensus message")?;
+    assert_eq!(host.unbonding_period(mock_consensus_state_id()), Some(challenge_period));
+    assert_eq!(host.pending_unbonding_period(mock_consensus_state_id()), None);
 
-    // Assert that request commitment was deleted
-    let commitment = hash_request::<H>(&request);
-    let res = host.request_commitment(commitment);
-    assert!(matches!(res, Err(..)));
     Ok(())
 }
 
-/*
-    Check correctness of router implementation
-*/
-
-/// Check that dispatcher stores commitments for outgoing requests and responses and rejects
-/// duplicate responses
-pub fn write_outgoing_commitments<H: IsmpHost>(
+/// Ensure a consensus proof that was already applied is rejected when it is submitted again,
+/// while new proofs are still accepted.
+pub fn duplicate_consensus_proof_check<H: IsmpHost>(
     host: &H,
-    dispatcher: &dyn IsmpDispatcher,
+    clock: &MockClock,
 ) -> Result<(), &'static str> {
-    let post = DispatchPost {
-        dest: StateMachine::Kusama(2000),
-        from: vec![0u8; 32],
-        to: vec![0u8; 32],
-        timeout_timestamp: 0,
-        data: vec![0u8; 64],
-        gas_limit: 0,
+    let intermediate_state = setup_aged_client(host, clock);
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    let consensus_message = |height| {
+        let commitment =
+            StateCommitmentHeight { commitment: intermediate_state.commitment, height };
+        mock_consensus_message(mock_consensus_proof(
+            intermediate_state.height.id.state_id,
+            vec![commitment],
+        ))
     };
-    let dispatch_request = DispatchRequest::Post(post);
-    // Dispatch the request the first time
-    dispatcher
-        .dispatch_request(dispatch_request)
-        .map_err(|_| "Dispatcher failed to dispatch request")?;
-    // Fetch commitment from storage
-    let post = Post {
-        source: host.host_state_machine(),
-        dest: StateMachine::Kusama(2000),
-        nonce: 0,
-        from: vec![0u8; 32],
-        to: vec![0u8; 32],
-        timeout_timestamp: 0,
-        data: vec![0u8; 64],
-        gas_limit: 0,
+    let next_height = intermediate_state.height.height + 1;
+
+    handle_incoming_message(host, consensus_message(next_height))
+        .map_err(|_| "Failed to process consensus message")?;
+
+    // A retry of the same proof after the challenge period is rejected
+    clock.advance(challenge_period * 2);
+    let res = handle_incoming_message(host, consensus_message(next_height));
+    assert!(matches!(res, Err(ismp::error::Error::DuplicateConsensusProof { .. })));
+
+    handle_incoming_message(host, consensus_message(next_height + 1))
+        .map_err(|_| "Failed to process a new consensus proof")?;
+
+    Ok(())
+}
+
+/// Ensure a consensus client rolling back to another fork only invalidates commitments above its
+/// new latest height that are still within their challenge period, and stale updates are ignored
+pub fn rollback_check<H: IsmpHost>(host: &H, clock: &MockClock) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let id = intermediate_state.height.id;
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    let commitment = |height| StateCommitmentHeight {
+        commitment: StateCommitment {
+            timestamp: 1000 + height,
+            overlay_root: None,
+            state_root: Default::default(),
+        },
+        height,
     };
-    let request = Request::Post(post);
-    let commitment = hash_request::<H>(&request);
-    host.request_commitment(commitment)
-        .map_err(|_| "Expected Request commitment to be found in storage")?;
-    let post = Post {
-        source: StateMachine::Kusama(2000),
-        dest: host.host_state_machine(),
-        nonce: 0,
-        from: vec![0u8; 32],
-        to: vec![0u8; 32],
-        timeout_timestamp: 0,
-        data: vec![0u8; 64],
-        gas_limit: 0,
+    let consensus_update = |commitments: Vec<StateCommitmentHeight>, rolled_back: bool| {
+        let mut proof = mock_consensus_proof(id.state_id, commitments);
+        if rolled_back {
+            proof = [MOCK_ROLLBACK_PROOF.to_vec(), proof].concat();
+        }
+        let res = handle_incoming_message(host, mock_consensus_message(proof));
+        clock.advance(challenge_period * 2);
+        res.map_err(|_| "Failed to process consensus message")
     };
-    let response = PostResponse { post, response: vec![] };
-    // Dispatch the outgoing response for the first time
-    dispatcher
+    let height_3 = StateMachineHeight { id, height: 3 };
+
+    consensus_update(vec![commitment(2), commitment(3)], false)?;
+    host.state_machine_commitment(height_3).map_err(|_| "Expected commitment to be stored")?;
+
+    // A stale update without a rollback signal is ignored
+    consensus_update(vec![commitment(2)], false)?;
+    assert_eq!(host.latest_commitment_height(id).unwrap(), 3);
+    host.state_machine_commitment(height_3)
+        .map_err(|_| "Expected stale update to leave commitments in place")?;
+
+    // Height 3 is past its challenge period, so it is final and survives the rollback
+    consensus_update(vec![commitment(2)], true)?;
+    assert_eq!(host.latest_commitment_height(id).unwrap(), 3);
+    host.state_machine_commitment(height_3)
+        .map_err(|_| "Expected finalized commitment to survive a rollback")?;
+
+    // Heights 2 and 3 are still within their challenge period, so the new fork replaces them
+    let height_2 = StateMachineHeight { id, height: 2 };
+    host.store_state_machine_update_time(height_2, host.timestamp()).unwrap();
+    host.store_state_machine_update_time(height_3, host.timestamp()).unwrap();
+    let fork = StateCommitmentHeight {
+        commitment: StateCommitment {
+            state_root: H256::repeat_byte(1),
+            ..commitment(2).commitment
+        },
+        height: 2,
+    };
+    consensus_update(vec![fork.clone()], true)?;
+    assert_eq!(host.latest_commitment_height(id).unwrap(), 2);
+    assert!(host.state_machine_commitment(height_3).is_err());
+    assert_eq!(host.state_machine_commitment(height_2).unwrap(), fork.commitment);
+    host.state_machine_commitment(intermediate_state.height)
+        .map_err(|_| "Expected commitment below the fork to be retained")?;
+
+    Ok(())
+}
+
+/// Ensure consensus updates pinned to a trusted state are rejected if the stored consensus state
+/// no longer matches it
+pub fn expected_trusted_state_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    setup_aged_client(host, clock);
+    let trusted_state = host.consensus_state(mock_consensus_state_id()).unwrap();
+    let consensus_message = |expected_trusted_state| {
+        Message::Consensus(ConsensusMessage {
+            consensus_proof: vec![],
+            consensus_state_id: mock_consensus_state_id(),
+            expected_trusted_state: Some(expected_trusted_state),
+            protocol_version: None,
+        })
+    };
+
+    let stale_state = H::keccak256(&[trusted_state.clone(), vec![1u8]].concat());
+    let res = handle_incoming_message(host, consensus_message(stale_state));
+    assert!(matches!(res, Err(ismp::error::Error::TrustedStateMismatch { .. })));
+
+    handle_incoming_message(host, consensus_message(H::keccak256(&trusted_state)))
+        .map_err(|_| "Failed to process consensus message")?;
+
+    Ok(())
+}
+
+/// Ensure consensus updates and proofs are rejected if the host clock is behind the last update
+/// time
+pub fn non_monotonic_clock_check<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let future_update_time = host.timestamp() + Duration::from_secs(60);
+    host.store_consensus_update_time(mock_consensus_state_id(), future_update_time).unwrap();
+
+    let res = handle_incoming_message(host, mock_consensus_message(vec![]));
+    assert!(matches!(res, Err(ismp::error::Error::NonMonotonicClock { .. })));
+    // The update time must not have moved backwards
+    assert_eq!(host.consensus_update_time(mock_consensus_state_id()).unwrap(), future_update_time);
+
+    // Proofs at a height updated in the future are rejected rather than underflowing
+    host.store_state_machine_update_time(intermediate_state.height, future_update_time).unwrap();
+    let post = mock_post(intermediate_state.height.id.state_id, host.host_state_machine(), 0);
+    let res =
+        handle_incoming_message(host, mock_request_message(vec![post], intermediate_state.height));
+    assert!(matches!(res, Err(ismp::error::Error::NonMonotonicClock { .. })));
+
+    Ok(())
+}
+
+/// Ensure hosts processing the same consensus message arrive at the same result, regardless of the
+/// order in which the consensus client returns the verified commitments.
+pub fn consensus_update_determinism_check<H: IsmpHost>(
+    (host_1, clock_1): (&H, &MockClock),
+    (host_2, clock_2): (&H, &MockClock),
+) -> Result<(), &'static str> {
+    let state_id = StateMachine::Ethereum(Ethereum::ExecutionLayer);
+    let commitment = |height, root: u8| StateCommitmentHeight {
+        commitment: StateCommitment {
+            timestamp: 1000 + height,
+            overlay_root: None,
+            state_root: [root; 32].into(),
+        },
+        height,
+    };
+    let ordered = vec![commitment(2, 1), commitment(3, 1), commitment(3, 2)];
+    let shuffled = vec![commitment(3, 2), commitment(2, 1), commitment(3, 1)];
+
+    let mut results = vec![];
+    for (host, clock, commitments) in [(host_1, clock_1, ordered), (host_2, clock_2, shuffled)] {
+        let intermediate_state = setup_aged_client(host, clock);
+        let consensus_message = mock_consensus_message(mock_consensus_proof(state_id, commitments));
+        let res = handle_incoming_message(host, consensus_message)
+            .map_err(|_| "Failed to process consensus message")?;
+        let MessageResult::ConsensusMessage(result) = res else {
+            return Err("Expected consensus update result")
+        };
+        let height = StateMachineHeight { id: intermediate_state.height.id, height: 3 };
+        let stored = host.state_machine_commitment(height).map_err(|_| "Missing commitment")?;
+        let new_commitments = result.new_commitments().to_vec();
+        let new_heights = new_commitments.iter().map(|(height, _)| height.height);
+        assert_eq!(new_heights.collect::<Vec<_>>(), vec![2, 3]);
+        results.push((result.state_updates, new_commitments, stored));
+    }
+
+    assert_eq!(results[0], results[1]);
+    Ok(())
+}
+
+/// Ensure heights are drained exactly once, after their challenge period elapses
+pub fn drain_elapsed_commitments_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    // The aged height itself has already cleared its challenge period
+    assert_eq!(host.drain_elapsed_commitments(host.timestamp()), vec![intermediate_state.height]);
+
+    let id = intermediate_state.height.id;
+    let height = StateMachineHeight { id, height: intermediate_state.height.height + 1 };
+    let commitment =
+        StateCommitmentHeight { commitment: intermediate_state.commitment, height: height.height };
+    handle_incoming_message(
+        host,
+        mock_consensus_message(mock_consensus_proof(id.state_id, vec![commitment])),
+    )
+    .map_err(|_| "Failed to process consensus message")?;
+
+    // The height is stored but not yet usable
+    assert!(host.drain_elapsed_commitments(host.timestamp()).is_empty());
+    clock.advance(challenge_period);
+    assert!(host.drain_elapsed_commitments(host.timestamp()).is_empty());
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(host.drain_elapsed_commitments(host.timestamp()), vec![height]);
+    // Each height is only finalized once
+    assert!(host.drain_elapsed_commitments(host.timestamp()).is_empty());
+
+    Ok(())
+}
+
+/// Ensure expired client rules are followed in consensus update
+pub fn check_client_expiry<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
+    let consensus_message = mock_consensus_message(vec![]);
+    setup_mock_client(host);
+    // Set the previous update time
+    let unbonding_period = host.unbonding_period(mock_consensus_state_id()).unwrap();
+    let previous_update_time = host.timestamp() - unbonding_period;
+    host.store_consensus_update_time(mock_consensus_state_id(), previous_update_time).unwrap();
+
+    let res = handle_incoming_message::<H>(host, consensus_message);
+    assert!(matches!(res, Err(ismp::error::Error::UnbondingPeriodElapsed { .. })));
+
+    Ok(())
+}
+
+/// Ensure proofs are rejected at heights below the earliest height the consensus client can verify
+pub fn pruned_height_check<H: IsmpHost>(host: &H, clock: &MockClock) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let height = intermediate_state.height;
+    let consensus_state = [MOCK_PRUNED_STATE.to_vec(), (height.height + 1).encode()].concat();
+    host.store_consensus_state(mock_consensus_state_id(), consensus_state.clone()).unwrap();
+
+    // The default height check follows the earliest verifiable height
+    let client = host.consensus_client(MOCK_CONSENSUS_CLIENT_ID).unwrap();
+    assert!(!client.supports_height(&consensus_state, height.height).unwrap());
+    assert!(client.supports_height(&consensus_state, height.height + 1).unwrap());
+
+    let post = mock_post(height.id.state_id, host.host_state_machine(), 0);
+    let res = handle_incoming_message(host, mock_request_message(vec![post], height));
+    assert!(matches!(res, Err(Error::HeightPruned { height: pruned }) if pruned == height));
+
+    Ok(())
+}
+
+/// Frozen state machine checks in message handlers
+pub fn frozen_check<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    // Set the previous update time
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    let previous_update_time = host.timestamp() - (challenge_period * 2);
+    host.store_consensus_update_time(mock_consensus_state_id(), previous_update_time).unwrap();
+    host.store_state_machine_update_time(intermediate_state.height, previous_update_time).unwrap();
+    let frozen_height = intermediate_state.height.prev().unwrap();
+    host.freeze_state_machine(frozen_height, FreezeReason::FraudProof).unwrap();
+
+    let post = mock_post(host.host_state_machine(), StateMachine::Kusama(2000), 0);
+    let request = Request::Post(post.clone());
+    // Request message handling check, for an incoming request addressed to the host
+    let incoming = Post { source: post.dest, dest: post.source, ..post.clone() };
+    let request_message = mock_request_message(vec![incoming], intermediate_state.height);
+
+    let res = handle_incoming_message(host, request_message);
+
+    assert!(matches!(
+        res,
+        Err(ismp::error::Error::FrozenStateMachine { reason: FreezeReason::FraudProof, .. })
+    ));
+
+    // Response message handling check
+    let response_message = Message::Response(ResponseMessage::Post {
+        responses: vec![Response::Post(post.respond(vec![]))],
+        proof: Proof { height: intermediate_state.height, proof: vec![] },
+        receipt_proof: None,
+    });
+
+    let res = handle_incoming_message(host, response_message);
+    assert!(matches!(res, Err(ismp::error::Error::FrozenStateMachine { .. })));
+
+    // Timeout mesaage handling check
+    let timeout_message = Message::Timeout(TimeoutMessage::Post {
+        requests: vec![request],
+        timeout_proof: Proof { height: intermediate_state.height, proof: vec![] },
+    });
+
+    let res = handle_incoming_message(host, timeout_message);
+    assert!(matches!(res, Err(ismp::error::Error::FrozenStateMachine { .. })));
+
+    Ok(())
+}
+
+/// Frozen consensus client checks in message handlers
+pub fn frozen_consensus_client_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+
+    // Freeze the consensus client through the fraud proof handler
+    let fraud_proof_message = Message::FraudProof(FraudProofMessage {
+        proof_1: vec![0u8],
+        proof_2: vec![1u8],
+        consensus_state_id: mock_consensus_state_id(),
+    });
+    handle_incoming_message(host, fraud_proof_message)
+        .map_err(|_| "Failed to freeze consensus client")?;
+
+    // Consensus message handling check
+    let res = handle_incoming_message(host, mock_consensus_message(vec![]));
+    assert!(matches!(res, Err(ismp::error::Error::FrozenConsensusClient { .. })));
+
+    let post = mock_post(intermediate_state.height.id.state_id, host.host_state_machine(), 0);
+    // Request message handling check
+    let request_message = mock_request_message(vec![post.clone()], intermediate_state.height);
+    let res = handle_incoming_message(host, request_message);
+    assert!(matches!(res, Err(ismp::error::Error::FrozenConsensusClient { .. })));
+
+    // Response message handling check
+    let response_message = Message::Response(ResponseMessage::Post {
+        responses: vec![Response::Post(PostResponse { post: post.clone(), response: vec![] })],
+        proof: Proof { height: intermediate_state.height, proof: vec![] },
+        receipt_proof: None,
+    });
+    let res = handle_incoming_message(host, response_message);
+    assert!(matches!(res, Err(ismp::error::Error::FrozenConsensusClient { .. })));
+
+    // Timeout mesaage handling check
+    let timeout_message = Message::Timeout(TimeoutMessage::Post {
+        requests: vec![Request::Post(post)],
+        timeout_proof: Proof { height: intermediate_state.height, proof: vec![] },
+    });
+    let res = handle_incoming_message(host, timeout_message);
+    assert!(matches!(res, Err(ismp::error::Error::FrozenConsensusClient { .. })));
+
+    Ok(())
+}
+
+/// Ensure only the governance authority can unfreeze a state machine, and that messages are
+/// accepted again after a successful unfreeze.
+pub fn unfreeze_check<H: IsmpHost>(host: &H, clock: &MockClock) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    host.freeze_state_machine(intermediate_state.height, FreezeReason::Manual).unwrap();
+    let unfreeze_message = UnfreezeStateMachineMessage { height: intermediate_state.height };
+
+    // Unauthorized accounts cannot unfreeze
+    let res = unfreeze_state_machine(host, &[0u8; 32], unfreeze_message.clone());
+    assert!(matches!(res, Err(ismp::error::Error::Unauthorized { .. })));
+    assert!(host.is_state_machine_frozen(intermediate_state.height).is_err());
+
+    let event = unfreeze_state_machine(host, &MOCK_GOVERNANCE_AUTHORITY, unfreeze_message.clone())
+        .map_err(|_| "Governance authority failed to unfreeze state machine")?;
+    assert_eq!(event.height, intermediate_state.height);
+    assert_eq!(event.authority, MOCK_GOVERNANCE_AUTHORITY.to_vec());
+    host.is_state_machine_frozen(intermediate_state.height)
+        .map_err(|_| "Expected state machine to be unfrozen")?;
+    let post = mock_post(intermediate_state.height.id.state_id, host.host_state_machine(), 0);
+    handle_incoming_message(host, mock_request_message(vec![post], intermediate_state.height))
+        .map_err(|_| "Messages should be accepted after unfreezing")?;
+
+    // Unfreezing a state machine that isn't frozen is a no-op
+    unfreeze_state_machine(host, &MOCK_GOVERNANCE_AUTHORITY, unfreeze_message)
+        .map_err(|_| "Unfreezing a state machine that isn't frozen should not fail")?;
+
+    Ok(())
+}
+
+/*
+    Request, response and timeout handling checks
+*/
+
+/// Ensure requests delivered after their soft delivery deadline are accepted but flagged as late
+pub fn late_delivery_check<H: IsmpHost>(host: &H, clock: &MockClock) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let post = |nonce, deliver_by| Post {
+        deliver_by,
+        ..mock_post(intermediate_state.height.id.state_id, host.host_state_machine(), nonce)
+    };
+    let deadline = intermediate_state.commitment.timestamp;
+    let request_message = mock_request_message(
+        vec![post(0, Some(deadline - 1)), post(1, Some(deadline)), post(2, None)],
+        intermediate_state.height,
+    );
+
+    let res = handle_incoming_message(host, request_message)
+        .map_err(|_| "Late requests should still be delivered")?;
+    let MessageResult::Request(results) = res else { return Err("Expected request results") };
+    let late = results.into_iter().map(|res| res.map(|success| success.late)).collect::<Vec<_>>();
+    assert_eq!(late, vec![Ok(true), Ok(false), Ok(false)]);
+
+    Ok(())
+}
+
+/// Ensure requests to unregistered modules fail individually without receipts, and that the
+/// results of a batch can be shipped to another process
+pub fn unknown_module_check<H: IsmpHost>(host: &H, clock: &MockClock) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let source = intermediate_state.height.id.state_id;
+    let unknown = Post {
+        to: MOCK_UNKNOWN_MODULE.to_vec(),
+        ..mock_post(source, host.host_state_machine(), 1)
+    };
+    let requests = vec![mock_post(source, host.host_state_machine(), 0), unknown.clone()];
+
+    let res =
+        handle_incoming_message(host, mock_request_message(requests, intermediate_state.height))
+            .map_err(|_| "Request batch should not be rejected")?;
+    assert_eq!(MessageResult::decode(&mut &res.encode()[..]).unwrap(), res);
+    let MessageResult::Request(results) = res else { return Err("Expected request results") };
+    assert!(matches!(results[..], [Ok(..), Err(..)]));
+    assert!(host.request_receipt(&Request::Post(unknown)).is_none());
+
+    Ok(())
+}
+
+/// Ensure proofs in a format the consensus client doesn't support are rejected
+pub fn proof_version_check<H: IsmpHost>(host: &H, clock: &MockClock) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let request_message = |nonce, version: u16| {
+        let post =
+            mock_post(intermediate_state.height.id.state_id, host.host_state_machine(), nonce);
+        Message::Request(RequestMessage {
+            requests: vec![post],
+            proof: Proof {
+                height: intermediate_state.height,
+                proof: [MOCK_VERSIONED_PROOF, &version.encode()].concat(),
+            },
+        })
+    };
+
+    let res = handle_incoming_message(host, request_message(0, 2));
+    assert!(matches!(res, Err(Error::UnsupportedProofVersion { version: 2, supported })
+        if supported == vec![0, 1]));
+
+    let res = handle_incoming_message(host, request_message(1, 1))
+        .map_err(|_| "Supported proof versions should be accepted")?;
+    let MessageResult::Request(results) = res else { return Err("Expected request results") };
+    assert!(matches!(results[..], [Ok(..)]));
+
+    Ok(())
+}
+
+/// Ensure delivered request nonces are reported per direction as ranges of consecutive nonces
+pub fn delivered_nonces_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let source = intermediate_state.height.id.state_id;
+    let post = |nonce| mock_post(source, host.host_state_machine(), nonce);
+    let request_message =
+        mock_request_message(vec![post(0), post(1), post(3)], intermediate_state.height);
+    handle_incoming_message(host, request_message).map_err(|_| "Failed to deliver requests")?;
+
+    assert_eq!(host.delivered_nonces(source, host.host_state_machine()), vec![0..=1, 3..=3]);
+    assert!(host.delivered_nonces(host.host_state_machine(), source).is_empty());
+
+    Ok(())
+}
+
+/// Ensure requests addressed to another state machine are rejected without receipts
+pub fn wrong_destination_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let post = mock_post(intermediate_state.height.id.state_id, StateMachine::Kusama(2001), 0);
+    let request_message = mock_request_message(vec![post.clone()], intermediate_state.height);
+
+    // Verification without delivery applies the same checks
+    let msg = RequestMessage {
+        requests: vec![post.clone()],
+        proof: Proof { height: intermediate_state.height, proof: vec![] },
+    };
+    let res = verify_request_membership(host, &msg);
+    assert!(matches!(res, Err(Error::WrongDestination { .. })));
+
+    let res = handle_incoming_message(host, request_message);
+    assert!(matches!(
+        res,
+        Err(Error::WrongDestination { expected, got: StateMachine::Kusama(2001) })
+            if expected == host.host_state_machine()
+    ));
+    assert!(host.request_receipt(&Request::Post(post)).is_none());
+
+    Ok(())
+}
+
+/// Ensure request batches mixing destinations are rejected as a whole
+pub fn heterogeneous_batch_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let source = intermediate_state.height.id.state_id;
+    let requests = vec![
+        mock_post(source, host.host_state_machine(), 0),
+        mock_post(source, StateMachine::Kusama(2000), 1),
+    ];
+    let request_message = mock_request_message(requests.clone(), intermediate_state.height);
+
+    let res = handle_incoming_message(host, request_message);
+    assert!(matches!(res, Err(Error::HeterogeneousBatch { nonce: 1, .. })));
+    assert!(host.request_receipt(&Request::Post(requests[0].clone())).is_none());
+
+    Ok(())
+}
+
+/// Ensure request and response messages with empty batches are rejected
+pub fn empty_message_check<H: IsmpHost>(host: &H) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let proof = Proof { height: intermediate_state.height, proof: vec![] };
+    let messages = [
+        Message::Request(RequestMessage { requests: vec![], proof: proof.clone() }),
+        Message::Response(ResponseMessage::Post {
+            responses: vec![],
+            proof: proof.clone(),
+            receipt_proof: None,
+        }),
+        Message::Response(ResponseMessage::Get { requests: vec![], proof }),
+    ];
+
+    for message in messages {
+        let res = handle_incoming_message(host, message);
+        assert!(matches!(res, Err(ismp::error::Error::EmptyMessage)));
+    }
+
+    Ok(())
+}
+
+/// Ensure GET responses are only accepted for reads at finalized, unfrozen heights
+pub fn get_read_height_finalized_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let dispatch_get = DispatchGet {
+        dest: intermediate_state.height.id.state_id,
+        from: vec![0u8; 32],
+        keys: vec![vec![1u8; 32]],
+        height: intermediate_state.height.height,
+        timeout_timestamp: 0,
+        gas_limit: 0,
+    };
+    dispatcher
+        .dispatch_request(DispatchRequest::Get(dispatch_get.clone()), &[0u8; 32])
+        .map_err(|_| "Get request dispatch failed")?;
+    let get = mock_get(host, dispatch_get.dest, 0, dispatch_get.keys, dispatch_get.height);
+    let response_message = || {
+        Message::Response(ResponseMessage::Get {
+            requests: vec![Request::Get(get.clone())],
+            proof: Proof { height: intermediate_state.height, proof: vec![] },
+        })
+    };
+
+    // The commitment at the read height is still within its challenge period
+    host.store_consensus_update_time(mock_consensus_state_id(), host.timestamp()).unwrap();
+    host.store_state_machine_update_time(intermediate_state.height, host.timestamp()).unwrap();
+    let res = handle_incoming_message(host, response_message());
+    assert!(matches!(res, Err(ismp::error::Error::ChallengePeriodNotElapsed { .. })));
+
+    // The state machine is frozen at the read height
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    clock.advance(challenge_period * 2);
+    host.freeze_state_machine(intermediate_state.height, FreezeReason::Manual).unwrap();
+    let res = handle_incoming_message(host, response_message());
+    assert!(matches!(res, Err(ismp::error::Error::FrozenStateMachine { .. })));
+
+    Ok(())
+}
+
+/// Ensure GET requests reading more keys than the host allows are rejected by the dispatcher and
+/// the response handler, while requests at the limit are accepted
+pub fn max_get_keys_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let max = host.max_get_keys().ok_or("Expected the host to bound get keys")?;
+    let intermediate_state = setup_mock_client(host);
+    let keys = |count| (0..count).map(|key: usize| key.to_be_bytes().to_vec()).collect::<Vec<_>>();
+    let dispatch_get = |count| DispatchGet {
+        dest: intermediate_state.height.id.state_id,
+        from: vec![0u8; 32],
+        keys: keys(count),
+        height: intermediate_state.height.height,
+        timeout_timestamp: 0,
+        gas_limit: 0,
+    };
+
+    dispatcher
+        .dispatch_request(DispatchRequest::Get(dispatch_get(max)), &[0u8; 32])
+        .map_err(|_| "Failed to dispatch get request at the limit")?;
+    let res = dispatcher.dispatch_request(DispatchRequest::Get(dispatch_get(max + 1)), &[0u8; 32]);
+    assert!(matches!(res, Err(ismp::error::Error::TooManyKeys { .. })));
+
+    let get = mock_get(
+        host,
+        intermediate_state.height.id.state_id,
+        1,
+        keys(max + 1),
+        intermediate_state.height.height,
+    );
+    let response_message = Message::Response(ResponseMessage::Get {
+        requests: vec![Request::Get(get)],
+        proof: Proof { height: intermediate_state.height, proof: vec![] },
+    });
+    let res = handle_incoming_message(host, response_message);
+    assert!(matches!(res, Err(ismp::error::Error::TooManyKeys { .. })));
+
+    Ok(())
+}
+
+/// Ensure GET responses can be verified without being dispatched or delivered
+pub fn verify_get_response_check<H: IsmpHost>(
+    host: &H,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let keys = vec![vec![1u8; 32]];
+    let get = mock_get(
+        host,
+        intermediate_state.height.id.state_id,
+        0,
+        keys.clone(),
+        intermediate_state.height.height,
+    );
+    let values = StateProofResult::from([(keys[0].clone(), Some(vec![0u8; 32]))]);
+    let response_message = ResponseMessage::Get {
+        requests: vec![Request::Get(get.clone())],
+        proof: Proof { height: intermediate_state.height, proof: values.encode() },
+    };
+
+    let verified = verify_get_response(host, &response_message)
+        .map_err(|_| "Failed to verify get response")?;
+    assert_eq!(verified, vec![GetResponse { get: get.clone(), values }]);
+    assert!(host.response_receipt(&Request::Get(get)).is_none());
+
+    Ok(())
+}
+
+/// Ensure GET responses are rejected unless the proof yields a value for every requested key
+pub fn missing_get_values_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let keys = vec![vec![1u8; 32], vec![2u8; 32]];
+    let get = mock_get(
+        host,
+        intermediate_state.height.id.state_id,
+        0,
+        keys.clone(),
+        intermediate_state.height.height,
+    );
+    let dispatch_get = DispatchGet {
+        dest: get.dest,
+        from: get.from.clone(),
+        keys: keys.clone(),
+        height: get.height,
+        timeout_timestamp: 0,
+        gas_limit: 0,
+    };
+    dispatcher
+        .dispatch_request(DispatchRequest::Get(dispatch_get), &[0u8; 32])
+        .map_err(|_| "Get request dispatch failed")?;
+
+    // The proof only yields a value for one of the two keys
+    let values = StateProofResult::from([(keys[0].clone(), Some(vec![0u8; 32]))]);
+    let response_message = ResponseMessage::Get {
+        requests: vec![Request::Get(get.clone())],
+        proof: Proof { height: intermediate_state.height, proof: values.encode() },
+    };
+    let res = verify_get_response(host, &response_message);
+    assert!(matches!(res, Err(Error::ProofValueCountMismatch { expected: 2, got: 1 })));
+    let res = handle_incoming_message(host, Message::Response(response_message));
+    assert!(matches!(res, Err(Error::ProofValueCountMismatch { expected: 2, got: 1 })));
+    assert!(host.response_receipt(&Request::Get(get)).is_none());
+
+    Ok(())
+}
+
+/// Ensure present and absent GET values are delivered to the module, and that the proof is
+/// reported to the host for auditing
+pub fn get_response_delivery_check(
+    host: &Host,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let keys = vec![vec![1u8; 32], vec![2u8; 32]];
+    let dest = intermediate_state.height.id.state_id;
+    let get = mock_get(host, dest, 0, keys.clone(), intermediate_state.height.height);
+    let dispatch_get = DispatchGet {
+        dest,
+        from: get.from.clone(),
+        keys: keys.clone(),
+        height: get.height,
+        timeout_timestamp: 0,
+        gas_limit: 0,
+    };
+    dispatcher
+        .dispatch_request(DispatchRequest::Get(dispatch_get), &[0u8; 32])
+        .map_err(|_| "Get request dispatch failed")?;
+
+    let values =
+        StateProofResult::from([(keys[0].clone(), Some(vec![0u8; 32])), (keys[1].clone(), None)]);
+    let response_message = Message::Response(ResponseMessage::Get {
+        requests: vec![Request::Get(get.clone())],
+        proof: Proof { height: intermediate_state.height, proof: values.encode() },
+    });
+    let res = handle_incoming_message(host, response_message)
+        .map_err(|_| "Failed to handle get response")?;
+    // GET response results carry the direction of the request
+    let MessageResult::Response(results) = res else { return Err("Expected response results") };
+    assert_eq!(results[0].source(), host.host_state_machine());
+    assert_eq!(results[0].dest(), dest);
+    // The proof is reported for auditing
+    let metadata = ProofMetadata {
+        root: intermediate_state.commitment.state_root,
+        depth: 0,
+        keys_verified: keys.len(),
+    };
+    assert_eq!(
+        *host.proof_metadata.borrow(),
+        vec![(hash_request::<Host>(&Request::Get(get)), metadata)]
+    );
+
+    let delivered = host.delivered_responses.borrow();
+    let [Response::Get(response)] = &delivered[..] else {
+        return Err("Expected a single get response")
+    };
+    assert_eq!(response.values, values);
+
+    Ok(())
+}
+
+/// Ensure the host's policy for requests from unsupported state machines is applied
+pub fn unknown_state_machine_policy_check(host: &Host) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let post = mock_post(StateMachine::Kusama(2000), host.host_state_machine(), 0);
+    let request_message = mock_request_message(vec![post.clone()], intermediate_state.height);
+
+    let res = handle_incoming_message(host, request_message.clone());
+    assert!(matches!(
+        res,
+        Err(Error::UnsupportedStateMachine { state_machine: StateMachine::Kusama(2000) })
+    ));
+
+    *host.unknown_policy.borrow_mut() = UnknownPolicy::Ignore;
+    let res = handle_incoming_message(host, request_message)
+        .map_err(|_| "Ignored requests should not fail")?;
+    assert_eq!(res, MessageResult::Request(vec![]));
+    assert!(host.request_receipt(&Request::Post(post)).is_none());
+
+    Ok(())
+}
+
+/// Ensure the outcome of every delivery to a module is recorded, and only accepted requests get
+/// a receipt
+pub fn delivery_outcome_check(host: &Host) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let accepted = mock_post(intermediate_state.height.id.state_id, host.host_state_machine(), 0);
+    let rejected = Post { nonce: 1, to: MOCK_REJECTING_MODULE.to_vec(), ..accepted.clone() };
+    let request_message =
+        mock_request_message(vec![accepted.clone(), rejected.clone()], intermediate_state.height);
+    handle_incoming_message(host, request_message).map_err(|_| "Failed to deliver requests")?;
+
+    let accepted_commitment = hash_request::<Host>(&Request::Post(accepted));
+    let rejected_commitment = hash_request::<Host>(&Request::Post(rejected.clone()));
+    let deliveries = host.deliveries.borrow().clone();
+    let [(delivered, DeliveryOutcome::Delivered), (refused, DeliveryOutcome::ModuleRejected(reason))] =
+        &deliveries[..]
+    else {
+        return Err("Expected one accepted and one rejected delivery")
+    };
+    assert_eq!(*delivered, accepted_commitment);
+    assert_eq!(*refused, rejected_commitment);
+    assert!(reason.contains("Request rejected"));
+    assert!(host.request_receipt(&Request::Post(rejected)).is_none());
+
+    Ok(())
+}
+
+/// Ensure a batch of messages looks each consensus client up once
+pub fn client_lookup_check(host: &Host) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let request_message = |nonce: u64| {
+        let post =
+            mock_post(intermediate_state.height.id.state_id, host.host_state_machine(), nonce);
+        mock_request_message(vec![post], intermediate_state.height)
+    };
+
+    let results = handle_incoming_messages(host, (0..3).map(request_message).collect());
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(*host.client_lookups.borrow(), 1);
+
+    // Messages handled individually look the client up every time
+    for nonce in 3..6 {
+        handle_incoming_message(host, request_message(nonce))
+            .map_err(|_| "Failed to deliver request")?;
+    }
+    assert_eq!(*host.client_lookups.borrow(), 4);
+
+    Ok(())
+}
+
+/// Ensure consensus updates revealing byzantine behaviour freeze the client and store the evidence
+pub fn byzantine_update_check(host: &Host) -> Result<(), &'static str> {
+    setup_aged_client(host, &host.clock);
+
+    let res = handle_incoming_message(host, mock_consensus_message(MOCK_BYZANTINE_PROOF.to_vec()))
+        .map_err(|_| "Byzantine updates should freeze the client")?;
+    assert_eq!(res, MessageResult::FrozenClient(mock_consensus_state_id()));
+    assert!(host.is_consensus_client_frozen(mock_consensus_state_id()).is_err());
+    assert_eq!(
+        host.byzantine_evidence.borrow().get(&mock_consensus_state_id()),
+        Some(&MOCK_BYZANTINE_PROOF.to_vec())
+    );
+
+    Ok(())
+}
+
+/// Ensure consensus messages can be dry run through the handler's checks without any side effects,
+/// and that proofs revealing byzantine behaviour are reported as errors
+pub fn dry_run_verify_check(host: &Host) -> Result<(), &'static str> {
+    setup_aged_client(host, &host.clock);
+    let client = host.consensus_client(MOCK_CONSENSUS_CLIENT_ID).unwrap();
+    let trusted_state = host.consensus_state(mock_consensus_state_id()).unwrap();
+    let message = |proof: &[u8]| ConsensusMessage {
+        consensus_proof: proof.to_vec(),
+        consensus_state_id: mock_consensus_state_id(),
+        expected_trusted_state: None,
+        protocol_version: None,
+    };
+
+    dry_run_update_client(host, message(&[])).map_err(|_| "Expected a valid proof to pass")?;
+    assert!(dry_run_update_client(host, message(&[0xff])).is_err());
+    let res = dry_run_update_client(host, message(MOCK_BYZANTINE_PROOF));
+    assert!(matches!(
+        res,
+        Err(Error::ByzantineBehaviour { consensus_state_id }) if consensus_state_id == mock_consensus_state_id()
+    ));
+
+    // The proof is valid against the trusted state, but the handler rejects a message built
+    // against a different one
+    let mismatched =
+        ConsensusMessage { expected_trusted_state: Some(H256::repeat_byte(1)), ..message(&[]) };
+    client
+        .dry_run_verify(host, mock_consensus_state_id(), trusted_state.clone(), vec![])
+        .map_err(|_| "Expected the proof alone to pass")?;
+    let res = dry_run_update_client(host, mismatched);
+    assert!(matches!(res, Err(Error::TrustedStateMismatch { .. })));
+
+    // Nothing was applied to the host
+    host.is_consensus_client_frozen(mock_consensus_state_id())
+        .map_err(|_| "Dry runs must not freeze the client")?;
+    assert!(host.byzantine_evidence.borrow().is_empty());
+    assert_eq!(host.consensus_state(mock_consensus_state_id()).unwrap(), trusted_state);
+    assert_eq!(host.last_consensus_proof(mock_consensus_state_id()), None);
+
+    Ok(())
+}
+
+/// Ensure optimistic updates are held pending and promoted only after their own challenge period
+pub fn optimistic_update_check(host: &Host) -> Result<(), &'static str> {
+    *host.optimistic_updates.borrow_mut() = true;
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    let id = intermediate_state.height.id;
+    let consensus_message = |height| {
+        let commitment =
+            StateCommitmentHeight { commitment: intermediate_state.commitment, height };
+        mock_consensus_message(mock_consensus_proof(id.state_id, vec![commitment]))
+    };
+    let first = StateMachineHeight { id, height: intermediate_state.height.height + 1 };
+    let second = StateMachineHeight { id, height: first.height + 1 };
+
+    // The update is held back rather than applied
+    let res = handle_incoming_message(host, consensus_message(first.height))
+        .map_err(|_| "Failed to process consensus message")?;
+    assert_eq!(
+        res,
+        MessageResult::ConsensusUpdatePending {
+            consensus_state_id: mock_consensus_state_id(),
+            promoted: None
+        }
+    );
+    assert!(host.state_machine_commitment(first).is_err());
+    let submitted_at = host.timestamp();
+
+    // It can't be promoted or replaced within its challenge period
+    assert_eq!(promote_pending_update(host, mock_consensus_state_id()).unwrap(), None);
+    let res = handle_incoming_message(host, consensus_message(second.height));
+    assert!(matches!(res, Err(Error::ChallengePeriodNotElapsed { .. })));
+
+    // The next update promotes it, and its commitments are usable straight away
+    host.clock.advance(challenge_period + Duration::from_secs(1));
+    let res = handle_incoming_message(host, consensus_message(second.height))
+        .map_err(|_| "Failed to process consensus message")?;
+    let MessageResult::ConsensusUpdatePending { promoted: Some(promoted), .. } = res else {
+        return Err("Expected the pending update to be promoted")
+    };
+    assert_eq!(promoted.new_commitments(), [(first, intermediate_state.commitment)]);
+    assert_eq!(host.state_machine_update_time(first).unwrap(), submitted_at);
+    assert!(host.state_machine_commitment(second).is_err());
+
+    host.clock.advance(challenge_period + Duration::from_secs(1));
+    let promoted = promote_pending_update(host, mock_consensus_state_id())
+        .map_err(|_| "Failed to promote pending update")?
+        .ok_or("Expected the pending update to be promoted")?;
+    assert_eq!(promoted.new_commitments(), [(second, intermediate_state.commitment)]);
+    assert!(host.pending_consensus_update(mock_consensus_state_id()).is_none());
+
+    Ok(())
+}
+
+/// Ensure finalized heights are acknowledged to peers, and acknowledgements from peers recorded
+pub fn consensus_ack_check(host: &Host, peer: &Host) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    // Acknowledge the aged height up front
+    acknowledge_finalized_commitments(host).map_err(|_| "Failed to acknowledge commitments")?;
+    host.emitted_acks.borrow_mut().clear();
+
+    let id = intermediate_state.height.id;
+    let height = StateMachineHeight { id, height: intermediate_state.height.height + 1 };
+    let commitment =
+        StateCommitmentHeight { commitment: intermediate_state.commitment, height: height.height };
+    handle_incoming_message(
+        host,
+        mock_consensus_message(mock_consensus_proof(id.state_id, vec![commitment])),
+    )
+    .map_err(|_| "Failed to process consensus message")?;
+
+    // Nothing is acknowledged before the challenge period elapses
+    assert!(acknowledge_finalized_commitments(host).unwrap().is_empty());
+    host.clock.advance(challenge_period + Duration::from_secs(1));
+
+    let ack = ConsensusAckMessage {
+        consensus_state_id: mock_consensus_state_id(),
+        height,
+        commitment_root: intermediate_state.commitment.state_root,
+    };
+    assert_eq!(acknowledge_finalized_commitments(host).unwrap(), vec![ack]);
+    assert_eq!(*host.emitted_acks.borrow(), vec![ack]);
+
+    // Acknowledgements carry no proof, so only authenticated peers may submit them
+    let res = record_consensus_ack(peer, &MOCK_GOVERNANCE_AUTHORITY, ack);
+    assert!(matches!(res, Err(Error::Unauthorized { .. })));
+    assert!(peer.received_acks.borrow().is_empty());
+
+    // A peer receiving the acknowledgement records it
+    record_consensus_ack(peer, &MOCK_CONSENSUS_PEER, ack)
+        .map_err(|_| "Failed to record acknowledgement")?;
+    assert_eq!(*peer.received_acks.borrow(), vec![ack]);
+
+    Ok(())
+}
+
+/// Ensure mutations buffered by a snapshot host can be rolled back, or committed to the host
+pub fn snapshot_check(host: &Host) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let previous_update_time = host.consensus_update_time(mock_consensus_state_id()).unwrap();
+    let id = intermediate_state.height.id;
+    let height = StateMachineHeight { id, height: intermediate_state.height.height + 1 };
+    let commitment =
+        StateCommitmentHeight { commitment: intermediate_state.commitment, height: height.height };
+    let consensus_message =
+        mock_consensus_message(mock_consensus_proof(id.state_id, vec![commitment]));
+
+    let snapshot = SnapshotHost::new(host.clone());
+    handle_incoming_message(&snapshot, consensus_message.clone())
+        .map_err(|_| "Failed to process consensus message")?;
+    assert!(snapshot.mutations().contains(&Mutation::LatestCommitmentHeight(height)));
+    assert_eq!(snapshot.latest_commitment_height(id).unwrap(), height.height);
+    assert!(host.state_machine_commitment(height).is_err());
+
+    snapshot.rollback();
+    assert!(snapshot.mutations().is_empty());
+    assert!(snapshot.state_machine_commitment(height).is_err());
+
+    handle_incoming_message(&snapshot, consensus_message)
+        .map_err(|_| "Failed to process consensus message")?;
+    snapshot.commit().map_err(|_| "Failed to commit snapshot")?;
+    assert_eq!(host.latest_commitment_height(id).unwrap(), height.height);
+    assert!(host.consensus_update_time(mock_consensus_state_id()).unwrap() > previous_update_time);
+    host.state_machine_commitment(height).map_err(|_| "Expected commitment to be stored")?;
+
+    Ok(())
+}
+
+/// Ensure the status of requests is reported by commitment, including changes buffered by a
+/// snapshot host
+pub fn request_status_check(
+    host: &Host,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let counterparty = intermediate_state.height.id.state_id;
+    let outgoing = (0..2u64)
+        .map(|nonce| {
+            dispatcher
+                .dispatch_request(
+                    DispatchRequest::Post(mock_dispatch_post(counterparty)),
+                    &[0u8; 32],
+                )
+                .map(|_| Request::Post(mock_post(host.host_state_machine(), counterparty, nonce)))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Failed to dispatch requests")?;
+    let incoming = Request::Post(mock_post(counterparty, host.host_state_machine(), 0));
+    let status =
+        |host: &dyn IsmpHost, request: &Request| host.request_status(hash_request::<Host>(request));
+
+    assert_eq!(status(host, &outgoing[0]), RequestStatus::Committed);
+    assert_eq!(status(host, &incoming), RequestStatus::Unknown);
+
+    // Status changes recorded by a snapshot are reported before they are committed
+    let snapshot = SnapshotHost::new(host.clone());
+    snapshot.store_response_receipt(&outgoing[0]).unwrap();
+    snapshot.delete_request_commitment(&outgoing[1]).unwrap();
+    snapshot.store_request_receipt(&incoming).unwrap();
+    let expected = [RequestStatus::Responded, RequestStatus::TimedOut, RequestStatus::Delivered];
+    for (request, expected) in outgoing.iter().chain([&incoming]).zip(expected) {
+        assert_eq!(status(&snapshot, request), expected);
+    }
+    assert_eq!(status(host, &outgoing[1]), RequestStatus::Committed);
+
+    snapshot.commit().map_err(|_| "Failed to commit snapshot")?;
+    for (request, expected) in outgoing.iter().chain([&incoming]).zip(expected) {
+        assert_eq!(status(host, request), expected);
+    }
+
+    Ok(())
+}
+
+/// Ensure hosts requiring the binding only deliver POST responses whose receipt proof attests to a
+/// receipt for the request on the source of the response
+pub fn response_binding_check(
+    host: &Host,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let dest = intermediate_state.height.id.state_id;
+    dispatcher
+        .dispatch_request(DispatchRequest::Post(mock_dispatch_post(dest)), &[0u8; 32])
+        .map_err(|_| "Failed to dispatch request")?;
+    let post = mock_post(host.host_state_machine(), dest, 0);
+    let response = Response::Post(PostResponse { post: post.clone(), response: vec![1u8; 32] });
+    let response_message = |receipt_proof: Option<Vec<u8>>| {
+        Message::Response(ResponseMessage::Post {
+            responses: vec![response.clone()],
+            proof: Proof { height: intermediate_state.height, proof: vec![] },
+            receipt_proof,
+        })
+    };
+    *host.response_binding.borrow_mut() = true;
+
+    // The receipts are proven separately from the responses, so the proof must be provided
+    let res = handle_incoming_message(host, response_message(None));
+    assert!(matches!(res, Err(Error::MissingReceiptProof)));
+
+    // The proof does not attest to a receipt for the request on the source of the response
+    let res = handle_incoming_message(host, response_message(Some(vec![])));
+    assert!(matches!(res, Err(Error::ResponseRequestBindingFailed { .. })));
+    assert!(host.delivered_responses.borrow().is_empty());
+
+    let receipt_key = request_receipt_key(hash_request::<Host>(&Request::Post(post)));
+    let values = StateProofResult::from([(receipt_key, Some(vec![1u8]))]);
+    handle_incoming_message(host, response_message(Some(values.encode())))
+        .map_err(|_| "Failed to deliver bound response")?;
+    assert_eq!(*host.delivered_responses.borrow(), vec![response]);
+
+    Ok(())
+}
+
+/// Ensure responses to requests that don't expect one are rejected, both on delivery and dispatch
+pub fn fire_and_forget_check(
+    host: &Host,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let dest = intermediate_state.height.id.state_id;
+    let dispatch_post = DispatchPost { expects_response: false, ..mock_dispatch_post(dest) };
+    dispatcher
+        .dispatch_request(DispatchRequest::Post(dispatch_post), &[0u8; 32])
+        .map_err(|_| "Failed to dispatch request")?;
+    let post = Post { expects_response: false, ..mock_post(host.host_state_machine(), dest, 0) };
+    // Whether a response is expected is committed to
+    let expecting = Post { expects_response: true, ..post.clone() };
+    assert_ne!(
+        hash_request::<Host>(&Request::Post(post.clone())),
+        hash_request::<Host>(&Request::Post(expecting.clone()))
+    );
+
+    let response = PostResponse { post: post.clone(), response: vec![1u8; 32] };
+    let response_message = Message::Response(ResponseMessage::Post {
+        responses: vec![Response::Post(response.clone())],
+        proof: Proof { height: intermediate_state.height, proof: vec![] },
+        receipt_proof: None,
+    });
+    let res = handle_incoming_message(host, response_message);
+    assert!(matches!(res, Err(Error::UnexpectedResponse { nonce: 0, .. })));
+    assert!(host.delivered_responses.borrow().is_empty());
+
+    // Nor can a fire-and-forget request be responded to on its destination
+    let res = dispatcher.dispatch_response(response);
+    assert!(matches!(res, Err(Error::UnexpectedResponse { nonce: 0, .. })));
+    dispatcher
+        .dispatch_response(PostResponse { post: expecting, response: vec![] })
+        .map_err(|_| "Failed to dispatch response")?;
+
+    Ok(())
+}
+
+/// Ensure all timeout post processing is correctly done.
+pub fn timeout_post_processing_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let challenge_period = host.challenge_period(mock_consensus_state_id()).unwrap();
+    let previous_update_time = host.timestamp() - (challenge_period * 2);
+    host.store_consensus_update_time(mock_consensus_state_id(), previous_update_time).unwrap();
+    host.store_state_machine_update_time(intermediate_state.height, previous_update_time).unwrap();
+    let dest = intermediate_state.height.id.state_id;
+    let timeout_timestamp = intermediate_state.commitment.timestamp;
+    let dispatch_post = DispatchPost { timeout_timestamp, ..mock_dispatch_post(dest) };
+    let post = Post { timeout_timestamp, ..mock_post(host.host_state_machine(), dest, 0) };
+    let request = Request::Post(post);
+    let dispatch_request = DispatchRequest::Post(dispatch_post);
+    dispatcher.dispatch_request(dispatch_request, &[0u8; 32]).unwrap();
+
+    // Timeout message handling check
+    let timeout_message = Message::Timeout(TimeoutMessage::Post {
+        requests: vec![request.clone()],
+        timeout_proof: Proof { height: intermediate_state.height, proof: vec![] },
+    });
+
+    handle_incoming_message(host, timeout_message).unwrap();
+
+    // Assert that request commitment was deleted
+    let commitment = hash_request::<H>(&request);
+    let res = host.request_commitment(commitment);
+    assert!(matches!(res, Err(..)));
+    Ok(())
+}
+
+/// Dispatch POST requests from the host to `dest` with the given timeouts, returning the
+/// dispatched requests
+fn dispatch_timeouts<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+    dest: StateMachine,
+    timeouts: impl IntoIterator<Item = u64>,
+) -> Result<Vec<Request>, &'static str> {
+    timeouts
+        .into_iter()
+        .enumerate()
+        .map(|(nonce, timeout_timestamp)| {
+            let dispatch_post = DispatchPost { timeout_timestamp, ..mock_dispatch_post(dest) };
+            dispatcher
+                .dispatch_request(DispatchRequest::Post(dispatch_post), &[0u8; 32])
+                .map_err(|_| "Failed to dispatch request")?;
+            let post = mock_post(host.host_state_machine(), dest, nonce as u64);
+            Ok(Request::Post(Post { timeout_timestamp, ..post }))
+        })
+        .collect()
+}
+
+/// Ensure requests in a timeout batch are processed individually, so a request that has not timed
+/// out does not prevent the others from being timed out.
+pub fn timeout_batch_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let timeout = intermediate_state.commitment.timestamp;
+    let requests = dispatch_timeouts(
+        host,
+        dispatcher,
+        intermediate_state.height.id.state_id,
+        [timeout, timeout + 1],
+    )?;
+
+    let timeout_message = Message::Timeout(TimeoutMessage::Post {
+        requests: requests.clone(),
+        timeout_proof: Proof { height: intermediate_state.height, proof: vec![] },
+    });
+    let res = handle_incoming_message(host, timeout_message)
+        .map_err(|_| "Timeout batch should not be rejected")?;
+    let MessageResult::Timeout(results) = res else { return Err("Expected timeout results") };
+    assert!(matches!(results[..], [Ok(..), Err(..)]));
+    let nonces = results.iter().map(|res| res.nonce()).collect::<Vec<_>>();
+    assert_eq!(nonces, requests.iter().map(|req| req.nonce()).collect::<Vec<_>>());
+
+    // Only the timed-out request commitment is deleted
+    assert!(host.request_commitment(hash_request::<H>(&requests[0])).is_err());
+    host.request_commitment(hash_request::<H>(&requests[1]))
+        .map_err(|_| "Expected request that has not timed out to be retained")?;
+    Ok(())
+}
+
+/// Ensure timeouts that have not elapsed are reported distinctly from timeouts whose proof shows
+/// the request was delivered
+pub fn early_timeout_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+    clock: &MockClock,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, clock);
+    let timed_out = intermediate_state.commitment.timestamp;
+    let requests = dispatch_timeouts(
+        host,
+        dispatcher,
+        intermediate_state.height.id.state_id,
+        [timed_out, timed_out + 100],
+    )?;
+
+    // The timed-out request was received on the destination
+    let receipt_key = request_receipt_key(hash_request::<H>(&requests[0]));
+    let proof = StateProofResult::from([(receipt_key, Some(vec![1u8]))]).encode();
+    let timeout_message = Message::Timeout(TimeoutMessage::Post {
+        requests: requests.clone(),
+        timeout_proof: Proof { height: intermediate_state.height, proof },
+    });
+
+    let res = handle_incoming_message(host, timeout_message)
+        .map_err(|_| "Timeout batch should not be rejected")?;
+    let MessageResult::Timeout(results) = res else { return Err("Expected timeout results") };
+    let [Err(failed), Err(early)] = &results[..] else {
+        return Err("Expected both timeouts to fail")
+    };
+    assert!(failed.msg.starts_with("RequestTimeoutVerificationFailed"));
+    assert!(early.msg.starts_with("RequestTimeoutNotElapsed"));
+    for request in &requests {
+        host.request_commitment(hash_request::<H>(request))
+            .map_err(|_| "Expected failed timeouts to retain their commitment")?;
+    }
+
+    Ok(())
+}
+
+/// Ensure the commitment of a request is retained while the module asks for its timeout to be
+/// retried or doesn't roll it back, and deleted once the timeout succeeds or fails for good
+pub fn timeout_retry_check(
+    host: &Host,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let timestamp = intermediate_state.commitment.timestamp;
+    let requests = dispatch_timeouts(
+        host,
+        dispatcher,
+        intermediate_state.height.id.state_id,
+        [timestamp, timestamp],
+    )?;
+    let timeout_message = |request: &Request| {
+        Message::Timeout(TimeoutMessage::Post {
+            requests: vec![request.clone()],
+            timeout_proof: Proof { height: intermediate_state.height, proof: vec![] },
+        })
+    };
+    let commitment = hash_request::<Host>(&requests[0]);
+
+    // The module asks for a retry on the first attempt and doesn't roll back the request on the
+    // second
+    *host.timeout_retries.borrow_mut() = 1;
+    *host.unrolled_timeouts.borrow_mut() = 1;
+    for _ in 0..2 {
+        let res = handle_incoming_message(host, timeout_message(&requests[0]))
+            .map_err(|_| "Timeout batch should not be rejected")?;
+        assert!(
+            matches!(&res, MessageResult::Timeout(results) if matches!(results[..], [Err(..)]))
+        );
+        host.request_commitment(commitment)
+            .map_err(|_| "Expected the commitment to be retained")?;
+    }
+
+    // The gas used by the module is reported once the timeout succeeds
+    let res = handle_incoming_message(host, timeout_message(&requests[0]))
+        .map_err(|_| "Timeout batch should not be rejected")?;
+    assert!(matches!(
+        &res,
+        MessageResult::Timeout(results)
+            if matches!(results[..], [Ok(DispatchSuccess { gas_used: MOCK_TIMEOUT_GAS, .. })])
+    ));
+    assert!(host.request_commitment(commitment).is_err());
+
+    // Any other failure is final, so the commitment is deleted
+    *host.timeout_failures.borrow_mut() = 1;
+    let res = handle_incoming_message(host, timeout_message(&requests[1]))
+        .map_err(|_| "Timeout batch should not be rejected")?;
+    assert!(matches!(&res, MessageResult::Timeout(results) if matches!(results[..], [Err(..)])));
+    assert!(host.request_commitment(hash_request::<Host>(&requests[1])).is_err());
+
+    Ok(())
+}
+
+/// Ensure the lifecycle of outgoing and incoming requests is traced through the host
+pub fn request_trace_check(
+    host: &Host,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_aged_client(host, &host.clock);
+    let counterparty = intermediate_state.height.id.state_id;
+    // An outgoing request that is later timed out
+    let outgoing = dispatch_timeouts(
+        host,
+        dispatcher,
+        counterparty,
+        [intermediate_state.commitment.timestamp],
+    )?;
+    let timeout_message = Message::Timeout(TimeoutMessage::Post {
+        requests: outgoing.clone(),
+        timeout_proof: Proof { height: intermediate_state.height, proof: vec![] },
+    });
+    let res = handle_incoming_message(host, timeout_message)
+        .map_err(|_| "Timeout batch should not be rejected")?;
+    // Timeout results are not swapped, they carry the direction of the request
+    let MessageResult::Timeout(results) = res else { return Err("Expected timeout results") };
+    assert_eq!(results[0].source(), host.host_state_machine());
+    assert_eq!(results[0].dest(), counterparty);
+
+    // An incoming request that is delivered
+    let incoming = mock_post(counterparty, host.host_state_machine(), 0);
+    let request_message = mock_request_message(vec![incoming.clone()], intermediate_state.height);
+    handle_incoming_message(host, request_message).map_err(|_| "Failed to deliver request")?;
+
+    let outgoing = hash_request::<Host>(&outgoing[0]);
+    let incoming = hash_request::<Host>(&Request::Post(incoming));
+    let traces = host
+        .traces
+        .borrow()
+        .iter()
+        .map(|event| (event.commitment, event.stage))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        traces,
+        vec![
+            (outgoing, TraceStage::Dispatched),
+            (outgoing, TraceStage::TimedOut),
+            (incoming, TraceStage::Delivered)
+        ]
+    );
+
+    Ok(())
+}
+
+/*
+    Check correctness of router implementation
+*/
+
+/// Check that dispatcher stores commitments for outgoing requests and responses and rejects
+/// duplicate responses and requests to unsupported destinations
+pub fn write_outgoing_commitments<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let dest = intermediate_state.height.id.state_id;
+    let dispatch_request = DispatchRequest::Post(mock_dispatch_post(dest));
+    // Dispatch the request the first time
+    dispatcher
+        .dispatch_request(dispatch_request, &[0u8; 32])
+        .map_err(|_| "Dispatcher failed to dispatch request")?;
+    // Fetch commitment from storage
+    let request = Request::Post(mock_post(host.host_state_machine(), dest, 0));
+    let commitment = hash_request::<H>(&request);
+    host.request_commitment(commitment)
+        .map_err(|_| "Expected Request commitment to be found in storage")?;
+    let post = mock_post(StateMachine::Kusama(2000), host.host_state_machine(), 0);
+    let response = PostResponse { post, response: vec![] };
+    // Dispatch the outgoing response for the first time
+    dispatcher
         .dispatch_response(response.clone())
         .map_err(|_| "Router failed to dispatch request")?;
     // Dispatch the same response a second time
     let err = dispatcher.dispatch_response(response);
     assert!(err.is_err(), "Expected router to return error for duplicate response");
 
+    // Dispatch a request to a state machine the host does not support
+    let post = mock_dispatch_post(StateMachine::Kusama(2000));
+    let res = dispatcher.dispatch_request(DispatchRequest::Post(post), &[0u8; 32]);
+    assert!(matches!(res, Err(ismp::error::Error::UnsupportedStateMachine { .. })));
+
+    Ok(())
+}
+
+/// Ensure requests addressed to the chain they originate from are rejected by both the dispatcher
+/// and the request handler
+pub fn self_dispatch_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let dispatch_post = mock_dispatch_post(host.host_state_machine());
+    let res = dispatcher.dispatch_request(DispatchRequest::Post(dispatch_post), &[0u8; 32]);
+    assert!(matches!(res, Err(ismp::error::Error::SelfDispatch { .. })));
+
+    let intermediate_state = setup_mock_client(host);
+    let state_id = intermediate_state.height.id.state_id;
+    let request_message =
+        mock_request_message(vec![mock_post(state_id, state_id, 0)], intermediate_state.height);
+    let res = handle_incoming_message(host, request_message);
+    assert!(matches!(res, Err(ismp::error::Error::SelfDispatch { .. })));
+
+    Ok(())
+}
+
+/// Ensure the dispatcher rejects requests whose `from` module doesn't authorize the caller, so one
+/// module can't dispatch requests as another
+pub fn unauthorized_source_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let dispatch_post = |from: Vec<u8>| {
+        DispatchRequest::Post(DispatchPost {
+            from,
+            ..mock_dispatch_post(intermediate_state.height.id.state_id)
+        })
+    };
+
+    let (module_a, module_b) = ([0u8; 32], [1u8; 32]);
+    dispatcher
+        .dispatch_request(dispatch_post(module_a.to_vec()), &module_a)
+        .map_err(|_| "Failed to dispatch request")?;
+    // Module A can't claim to be module B
+    let res = dispatcher.dispatch_request(dispatch_post(module_b.to_vec()), &module_a);
+    assert!(matches!(res, Err(Error::Unauthorized { account }) if account == module_a));
+
+    Ok(())
+}
+
+/// Ensure the dispatcher requires a destination module, allows empty data, and only allows an
+/// empty source module if the host permits anonymous dispatch
+pub fn empty_dispatch_fields_check(
+    host: &Host,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let dispatch_post = |from: Vec<u8>, to: Vec<u8>, data: Vec<u8>| {
+        DispatchRequest::Post(DispatchPost {
+            from,
+            to,
+            data,
+            ..mock_dispatch_post(intermediate_state.height.id.state_id)
+        })
+    };
+
+    // A POST without a destination module is rejected
+    let res = dispatcher
+        .dispatch_request(dispatch_post(vec![0u8; 32], vec![], vec![0u8; 64]), &[0u8; 32]);
+    assert!(matches!(res, Err(Error::EmptyModuleId { field }) if field == "to"));
+
+    // Empty data is a valid ping
+    dispatcher
+        .dispatch_request(dispatch_post(vec![0u8; 32], vec![0u8; 32], vec![]), &[0u8; 32])
+        .map_err(|_| "Empty data should be accepted")?;
+
+    // Anonymous sends are only allowed if the host permits them
+    let res = dispatcher.dispatch_request(dispatch_post(vec![], vec![0u8; 32], vec![0u8; 64]), &[]);
+    assert!(matches!(res, Err(Error::EmptyModuleId { field }) if field == "from"));
+    *host.anonymous_dispatch.borrow_mut() = true;
+    dispatcher
+        .dispatch_request(dispatch_post(vec![], vec![0u8; 32], vec![0u8; 64]), &[])
+        .map_err(|_| "Anonymous dispatch should be accepted when allowed")?;
+
+    // Routers only resolve registered modules, anonymous requests must not be looked up
+    let mut registry = ModuleRegistry::default();
+    registry
+        .register(H256([0u8; 32]), Rc::new(MockModule(host.clone())))
+        .map_err(|_| "Failed to register module")?;
+    *host.registry.borrow_mut() = Some(registry);
+    dispatcher
+        .dispatch_request(dispatch_post(vec![], vec![0u8; 32], vec![0u8; 64]), &[])
+        .map_err(|_| "Anonymous dispatch should not require a registered module")?;
+    dispatcher
+        .dispatch_request(dispatch_post(vec![0u8; 32], vec![0u8; 32], vec![0u8; 64]), &[0u8; 32])
+        .map_err(|_| "Failed to dispatch from a registered module")?;
+    let res = dispatcher
+        .dispatch_request(dispatch_post(vec![1u8; 32], vec![0u8; 32], vec![]), &[1u8; 32]);
+    assert!(matches!(res, Err(Error::UnknownModule { .. })));
+
+    Ok(())
+}
+
+/// Ensure request metadata is committed to and its length bounded on dispatch and delivery
+pub fn request_metadata_check<H: IsmpHost>(
+    host: &H,
+    dispatcher: &dyn IsmpDispatcher,
+) -> Result<(), &'static str> {
+    let intermediate_state = setup_mock_client(host);
+    let dest = intermediate_state.height.id.state_id;
+    let post = mock_post(host.host_state_machine(), dest, 0);
+
+    // Metadata is committed to and can't be confused with the response module
+    let with_meta = Post { meta: Some(vec![3u8; 32]), ..post.clone() };
+    let with_response_to = Post { response_to: Some(vec![3u8; 32]), ..post.clone() };
+    let hashes = [&post, &with_meta, &with_response_to]
+        .map(|post| hash_request::<H>(&Request::Post(post.clone())));
+    assert_ne!(hashes[0], hashes[1]);
+    assert_ne!(hashes[1], hashes[2]);
+
+    let dispatch_post = |meta: Vec<u8>| {
+        DispatchRequest::Post(DispatchPost { meta: Some(meta), ..mock_dispatch_post(dest) })
+    };
+    dispatcher
+        .dispatch_request(dispatch_post(vec![0u8; MAX_POST_META_LEN]), &[0u8; 32])
+        .map_err(|_| "Metadata at the limit should be accepted")?;
+    let res =
+        dispatcher.dispatch_request(dispatch_post(vec![0u8; MAX_POST_META_LEN + 1]), &[0u8; 32]);
+    assert!(
+        matches!(res, Err(Error::MetadataTooLarge { len, .. }) if len == MAX_POST_META_LEN + 1)
+    );
+
+    // Incoming requests are held to the same limit
+    let incoming = Post {
+        meta: Some(vec![0u8; MAX_POST_META_LEN + 1]),
+        ..mock_post(dest, host.host_state_machine(), 0)
+    };
+    let res = handle_incoming_message(
+        host,
+        mock_request_message(vec![incoming], intermediate_state.height),
+    );
+    assert!(matches!(res, Err(Error::MetadataTooLarge { .. })));
+
     Ok(())
 }
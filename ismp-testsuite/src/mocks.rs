@@ -1,25 +1,29 @@
+use codec::Decode;
 use ismp::{
     consensus::{
-        ConsensusClient, ConsensusClientId, ConsensusStateId, StateCommitment, StateMachineClient,
-        StateMachineHeight, StateMachineId, VerifiedCommitments,
+        ConsensusClient, ConsensusClientId, ConsensusStateId, ConsensusStateInfo,
+        PendingConsensusUpdate, ProofMetadata, StateCommitment, StateMachineClient,
+        StateMachineHeight, StateMachineId, StateProof, StateProofResult, VerifiedCommitments,
+        VerifyOutcome,
     },
     error::Error,
-    host::{IsmpHost, StateMachine},
-    messaging::Proof,
-    module::IsmpModule,
+    events::{DeliveryOutcome, StateMachineUnfrozen, TraceEvent, TraceStage},
+    host::{FreezeReason, IsmpHost, RequestStatus, StateMachine, UnknownPolicy},
+    messaging::{ConsensusAckMessage, Proof},
+    module::{IsmpModule, ModuleId, ModuleRegistry, TimeoutOutcome},
     router::{
-        DispatchRequest, Get, IsmpDispatcher, IsmpRouter, Post, PostResponse, Request,
-        RequestResponse, Response,
+        validate_dispatch, DispatchRequest, Get, IsmpDispatcher, IsmpRouter, Post, PostResponse,
+        Request, RequestResponseRef, Response, REQUEST_VERSION,
     },
-    util::{hash_request, hash_response, Keccak256},
+    util::{compact_nonces, hash_request, hash_response, request_receipt_key, Keccak256},
 };
 use primitive_types::H256;
 use std::{
     cell::RefCell,
     collections::{BTreeMap, BTreeSet, HashMap},
+    ops::RangeInclusive,
     rc::Rc,
-    sync::Arc,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::Duration,
 };
 
 #[derive(Default)]
@@ -27,20 +31,78 @@ pub struct MockClient;
 
 pub const MOCK_CONSENSUS_CLIENT_ID: [u8; 4] = [1u8; 4];
 
+pub const MOCK_GOVERNANCE_AUTHORITY: [u8; 32] = [2u8; 32];
+
+pub const MOCK_CONSENSUS_PEER: [u8; 32] = [3u8; 32];
+
+pub const MOCK_UNKNOWN_MODULE: [u8; 32] = [4u8; 32];
+
+pub const MOCK_REJECTING_MODULE: [u8; 32] = [5u8; 32];
+
+pub const MOCK_MAX_GET_KEYS: usize = 16;
+
+pub const MOCK_TIMEOUT_GAS: u64 = 21_000;
+
+pub const MOCK_BYZANTINE_PROOF: &[u8] = b"byzantine";
+
+pub const MOCK_STALE_PROOF: &[u8] = b"stale";
+
+/// Mock consensus proofs starting with this prefix roll the client back to another fork, they are
+/// followed by the scale encoded commitments on that fork
+pub const MOCK_ROLLBACK_PROOF: &[u8] = b"rollback";
+
+/// Mock consensus states starting with this prefix are followed by the scale encoded earliest
+/// height they can verify
+pub const MOCK_PRUNED_STATE: &[u8] = b"pruned";
+
+/// Mock state proofs starting with this prefix are followed by their scale encoded format version
+pub const MOCK_VERSIONED_PROOF: &[u8] = b"version";
+
 #[derive(codec::Encode, codec::Decode)]
 pub struct MockConsensusState {
     frozen_height: Option<u64>,
 }
 
 impl ConsensusClient for MockClient {
+    fn consensus_client_id(&self) -> ConsensusClientId {
+        MOCK_CONSENSUS_CLIENT_ID
+    }
+
+    fn supported_proof_versions(&self) -> Vec<u16> {
+        vec![0, 1]
+    }
+
+    fn proof_version(&self, proof: &[u8]) -> Result<u16, Error> {
+        let Some(mut version) = proof.strip_prefix(MOCK_VERSIONED_PROOF) else { return Ok(0) };
+        u16::decode(&mut version).map_err(|e| Error::ImplementationSpecific(format!("{e:?}")))
+    }
+
     fn verify_consensus(
         &self,
         _host: &dyn IsmpHost,
         _consensus_state_id: ConsensusStateId,
-        _trusted_consensus_state: Vec<u8>,
-        _proof: Vec<u8>,
-    ) -> Result<(Vec<u8>, VerifiedCommitments), Error> {
-        Ok(Default::default())
+        trusted_consensus_state: Vec<u8>,
+        proof: Vec<u8>,
+    ) -> Result<VerifyOutcome, Error> {
+        if proof == MOCK_BYZANTINE_PROOF {
+            return Ok(VerifyOutcome::Byzantine(proof))
+        }
+        // A buggy client that hands back the trusted state
+        if proof == MOCK_STALE_PROOF {
+            return Ok(VerifyOutcome::Verified(trusted_consensus_state, Default::default()))
+        }
+        // A non-empty mock proof is the scale encoded commitments it attests to
+        if proof.is_empty() {
+            return Ok(VerifyOutcome::Verified(Default::default(), Default::default()))
+        }
+        if let Some(commitments) = proof.strip_prefix(MOCK_ROLLBACK_PROOF) {
+            let commitments = VerifiedCommitments::decode(&mut &commitments[..])
+                .map_err(|e| Error::ImplementationSpecific(format!("{e:?}")))?;
+            return Ok(VerifyOutcome::RolledBack(Default::default(), commitments))
+        }
+        let commitments = VerifiedCommitments::decode(&mut &proof[..])
+            .map_err(|e| Error::ImplementationSpecific(format!("{e:?}")))?;
+        Ok(VerifyOutcome::Verified(Default::default(), commitments))
     }
 
     fn verify_fraud_proof(
@@ -53,6 +115,31 @@ impl ConsensusClient for MockClient {
         Ok(())
     }
 
+    fn consensus_state_height(&self, consensus_state: &[u8]) -> Result<Option<u64>, Error> {
+        // A non-empty mock consensus state is its scale encoded height
+        if consensus_state.is_empty() {
+            return Ok(None)
+        }
+        u64::decode(&mut &consensus_state[..])
+            .map(Some)
+            .map_err(|e| Error::ConsensusStateDecodeFailed(format!("{e:?}")))
+    }
+
+    fn earliest_verifiable_height(&self, consensus_state: &[u8]) -> Result<u64, Error> {
+        let Some(mut earliest) = consensus_state.strip_prefix(MOCK_PRUNED_STATE) else {
+            return Ok(0)
+        };
+        u64::decode(&mut earliest).map_err(|e| Error::ImplementationSpecific(format!("{e:?}")))
+    }
+
+    fn describe_state(&self, _consensus_state: &[u8]) -> Result<ConsensusStateInfo, Error> {
+        Ok(ConsensusStateInfo {
+            latest_height: 0,
+            frozen: false,
+            next_unbonding: Duration::from_secs(60 * 60 * 60),
+        })
+    }
+
     fn state_machine(&self, _id: StateMachine) -> Result<Box<dyn StateMachineClient>, Error> {
         Ok(Box::new(MockStateMachineClient))
     }
@@ -64,25 +151,61 @@ impl StateMachineClient for MockStateMachineClient {
     fn verify_membership(
         &self,
         _host: &dyn IsmpHost,
-        _item: RequestResponse,
+        _item: RequestResponseRef<'_>,
         _root: StateCommitment,
         _proof: &Proof,
     ) -> Result<(), Error> {
         Ok(())
     }
 
-    fn state_trie_key(&self, _request: Vec<Request>) -> Vec<Vec<u8>> {
-        Default::default()
+    fn state_trie_key(&self, requests: Vec<Request>) -> Vec<Vec<u8>> {
+        requests.iter().map(|req| request_receipt_key(hash_request::<Host>(req))).collect()
     }
 
     fn verify_state_proof(
         &self,
         _host: &dyn IsmpHost,
-        _keys: Vec<Vec<u8>>,
+        state_proof: StateProof,
         _root: StateCommitment,
-        _proof: &Proof,
-    ) -> Result<BTreeMap<Vec<u8>, Option<Vec<u8>>>, Error> {
-        Ok(Default::default())
+        proof: &Proof,
+    ) -> Result<StateProofResult, Error> {
+        // An empty mock proof attests to the absence of every key, a non-empty one is the scale
+        // encoded values it attests to
+        if proof.proof.is_empty() {
+            return Ok(state_proof.keys.into_iter().map(|key| (key, None)).collect())
+        }
+        StateProofResult::decode(&mut &proof.proof[..])
+            .map_err(|e| Error::ImplementationSpecific(format!("{e:?}")))
+    }
+}
+
+/// A manually advanced clock, so that tests depending on the host time are deterministic
+#[derive(Clone, Debug)]
+pub struct MockClock(Rc<RefCell<Duration>>);
+
+impl MockClock {
+    /// The time a clock starts at by default, in seconds since the unix epoch
+    pub const BASE: u64 = 1_700_000_000;
+
+    /// Create a clock starting at the given time
+    pub fn new(base: Duration) -> Self {
+        Self(Rc::new(RefCell::new(base)))
+    }
+
+    /// Returns the current time
+    pub fn now(&self) -> Duration {
+        *self.0.borrow()
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, duration: Duration) {
+        *self.0.borrow_mut() += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(Self::BASE))
     }
 }
 
@@ -91,13 +214,60 @@ pub struct Host {
     requests: Rc<RefCell<BTreeSet<H256>>>,
     receipts: Rc<RefCell<HashMap<H256, ()>>>,
     responses: Rc<RefCell<BTreeSet<H256>>>,
+    responded: Rc<RefCell<BTreeSet<H256>>>,
+    timed_out: Rc<RefCell<BTreeSet<H256>>>,
     consensus_clients: Rc<RefCell<HashMap<ConsensusStateId, ConsensusClientId>>>,
     consensus_states: Rc<RefCell<HashMap<ConsensusStateId, Vec<u8>>>>,
     state_commitments: Rc<RefCell<HashMap<StateMachineHeight, StateCommitment>>>,
     consensus_update_time: Rc<RefCell<HashMap<ConsensusStateId, Duration>>>,
-    frozen_state_machines: Rc<RefCell<HashMap<StateMachineId, StateMachineHeight>>>,
+    state_machine_update_times: Rc<RefCell<HashMap<StateMachineHeight, Duration>>>,
+    unfinalized_heights: Rc<RefCell<BTreeMap<StateMachineHeight, Duration>>>,
+    frozen_state_machines: Rc<RefCell<HashMap<StateMachineId, (StateMachineHeight, FreezeReason)>>>,
+    frozen_consensus_clients: Rc<RefCell<BTreeSet<ConsensusStateId>>>,
     latest_state_height: Rc<RefCell<HashMap<StateMachineId, u64>>>,
+    unbonding_periods: Rc<RefCell<HashMap<ConsensusStateId, u64>>>,
+    pending_unbonding_periods: Rc<RefCell<HashMap<ConsensusStateId, u64>>>,
+    last_consensus_proofs: Rc<RefCell<HashMap<ConsensusStateId, H256>>>,
+    pending_consensus_updates: Rc<RefCell<HashMap<ConsensusStateId, PendingConsensusUpdate>>>,
+    /// Whether consensus updates are applied optimistically
+    pub optimistic_updates: Rc<RefCell<bool>>,
     nonce: Rc<RefCell<u64>>,
+    /// Responses that have been delivered to the mock module
+    pub delivered_responses: Rc<RefCell<Vec<Response>>>,
+    /// Number of timeouts the mock module should fail to process
+    pub timeout_failures: Rc<RefCell<u32>>,
+    /// Number of timeouts the mock module should ask to be retried
+    pub timeout_retries: Rc<RefCell<u32>>,
+    /// Number of timeouts the mock module should process without rolling back the request
+    pub unrolled_timeouts: Rc<RefCell<u32>>,
+    /// Request lifecycle traces recorded by the host
+    pub traces: Rc<RefCell<Vec<TraceEvent>>>,
+    /// Whether requests may be dispatched with an empty `from`
+    pub anonymous_dispatch: Rc<RefCell<bool>>,
+    /// Whether POST responses must prove their source received the request
+    pub response_binding: Rc<RefCell<bool>>,
+    /// The clock backing the host timestamp
+    pub clock: MockClock,
+    /// Evidence of byzantine behaviour for frozen consensus states
+    pub byzantine_evidence: Rc<RefCell<HashMap<ConsensusStateId, Vec<u8>>>>,
+    /// The outcome of every request delivered to a module, in order
+    pub deliveries: Rc<RefCell<Vec<(H256, DeliveryOutcome)>>>,
+    allowed_proxies: Rc<RefCell<Vec<StateMachine>>>,
+    /// How requests from unsupported state machines are handled
+    pub unknown_policy: Rc<RefCell<UnknownPolicy>>,
+    /// Number of times a consensus client has been looked up
+    pub client_lookups: Rc<RefCell<usize>>,
+    delivered_requests: Rc<RefCell<BTreeSet<(StateMachine, StateMachine, u64)>>>,
+    /// Metadata of the state proofs verified for GET responses
+    pub proof_metadata: Rc<RefCell<Vec<(H256, ProofMetadata)>>>,
+    /// Acknowledgements sent to peer hosts
+    pub emitted_acks: Rc<RefCell<Vec<ConsensusAckMessage>>>,
+    /// Acknowledgements received from peer hosts
+    pub received_acks: Rc<RefCell<Vec<ConsensusAckMessage>>>,
+    /// State machines unfrozen by governance
+    pub unfrozen_state_machines: Rc<RefCell<Vec<StateMachineUnfrozen>>>,
+    /// Modules to route to instead of the catch-all mock router
+    pub registry: Rc<RefCell<Option<ModuleRegistry>>>,
 }
 
 impl IsmpHost for Host {
@@ -106,11 +276,7 @@ impl IsmpHost for Host {
     }
 
     fn latest_commitment_height(&self, id: StateMachineId) -> Result<u64, Error> {
-        self.latest_state_height
-            .borrow()
-            .get(&id)
-            .copied()
-            .ok_or_else(|| Error::ImplementationSpecific("latest height not found".into()))
+        self.latest_state_height.borrow().get(&id).copied().ok_or(Error::NoCommitmentHeight { id })
     }
 
     fn state_machine_commitment(
@@ -136,11 +302,9 @@ impl IsmpHost for Host {
         &self,
         state_machine_height: StateMachineHeight,
     ) -> Result<Duration, Error> {
-        self.consensus_update_time
-            .borrow()
-            .get(&state_machine_height.id.consensus_state_id)
-            .copied()
-            .ok_or_else(|| Error::ImplementationSpecific("Consensus update time not found".into()))
+        self.state_machine_update_times.borrow().get(&state_machine_height).copied().ok_or_else(
+            || Error::ImplementationSpecific("State machine update time not found".into()),
+        )
     }
 
     fn consensus_client_id(
@@ -159,24 +323,28 @@ impl IsmpHost for Host {
     }
 
     fn timestamp(&self) -> Duration {
-        SystemTime::now().duration_since(UNIX_EPOCH).unwrap()
+        self.clock.now()
     }
 
     fn is_state_machine_frozen(&self, machine: StateMachineHeight) -> Result<(), Error> {
-        let val = self
+        let reason = self
             .frozen_state_machines
             .borrow()
             .get(&machine.id)
-            .map(|frozen_height| machine.height >= frozen_height.height)
-            .unwrap_or(false);
-        if val {
-            Err(Error::FrozenStateMachine { height: machine })?;
+            .filter(|(frozen_height, _)| machine.height >= frozen_height.height)
+            .map(|(_, reason)| *reason);
+        if let Some(reason) = reason {
+            Err(Error::FrozenStateMachine { height: machine, reason })?;
         }
 
         Ok(())
     }
 
-    fn is_consensus_client_frozen(&self, _client: ConsensusStateId) -> Result<(), Error> {
+    fn is_consensus_client_frozen(&self, client: ConsensusStateId) -> Result<(), Error> {
+        if self.frozen_consensus_clients.borrow().contains(&client) {
+            Err(Error::FrozenConsensusClient { consensus_state_id: client })?
+        }
+
         Ok(())
     }
 
@@ -204,6 +372,34 @@ impl IsmpHost for Host {
         self.receipts.borrow().get(&hash).map(|_| ())
     }
 
+    fn delivered_nonces(
+        &self,
+        source: StateMachine,
+        dest: StateMachine,
+    ) -> Vec<RangeInclusive<u64>> {
+        compact_nonces(
+            self.delivered_requests
+                .borrow()
+                .iter()
+                .filter(|(from, to, _)| (*from, *to) == (source, dest))
+                .map(|(_, _, nonce)| *nonce),
+        )
+    }
+
+    fn request_status(&self, commitment: H256) -> RequestStatus {
+        if self.responded.borrow().contains(&commitment) {
+            RequestStatus::Responded
+        } else if self.timed_out.borrow().contains(&commitment) {
+            RequestStatus::TimedOut
+        } else if self.receipts.borrow().contains_key(&commitment) {
+            RequestStatus::Delivered
+        } else if self.requests.borrow().contains(&commitment) {
+            RequestStatus::Committed
+        } else {
+            RequestStatus::Unknown
+        }
+    }
+
     fn store_consensus_state_id(
         &self,
         consensus_state_id: ConsensusStateId,
@@ -220,12 +416,30 @@ impl IsmpHost for Host {
 
     fn store_unbonding_period(
         &self,
-        _consensus_state_id: ConsensusStateId,
-        _period: u64,
+        consensus_state_id: ConsensusStateId,
+        period: u64,
+    ) -> Result<(), Error> {
+        self.unbonding_periods.borrow_mut().insert(consensus_state_id, period);
+        Ok(())
+    }
+
+    fn store_pending_unbonding_period(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        period: Option<u64>,
     ) -> Result<(), Error> {
+        let mut pending = self.pending_unbonding_periods.borrow_mut();
+        match period {
+            Some(period) => pending.insert(consensus_state_id, period),
+            None => pending.remove(&consensus_state_id),
+        };
         Ok(())
     }
 
+    fn pending_unbonding_period(&self, consensus_state_id: ConsensusStateId) -> Option<u64> {
+        self.pending_unbonding_periods.borrow().get(&consensus_state_id).copied()
+    }
+
     fn store_consensus_update_time(
         &self,
         id: ConsensusStateId,
@@ -237,12 +451,30 @@ impl IsmpHost for Host {
 
     fn store_state_machine_update_time(
         &self,
-        _state_machine_height: StateMachineHeight,
-        _timestamp: Duration,
+        state_machine_height: StateMachineHeight,
+        timestamp: Duration,
     ) -> Result<(), Error> {
+        self.state_machine_update_times.borrow_mut().insert(state_machine_height, timestamp);
+        self.unfinalized_heights.borrow_mut().insert(state_machine_height, timestamp);
         Ok(())
     }
 
+    fn drain_elapsed_commitments(&self, now: Duration) -> Vec<StateMachineHeight> {
+        let mut unfinalized = self.unfinalized_heights.borrow_mut();
+        let elapsed = unfinalized
+            .iter()
+            .filter(|(height, update_time)| {
+                self.challenge_period(height.id.consensus_state_id)
+                    .is_some_and(|period| now.saturating_sub(**update_time) > period)
+            })
+            .map(|(height, _)| *height)
+            .collect::<Vec<_>>();
+        for height in &elapsed {
+            unfinalized.remove(height);
+        }
+        elapsed
+    }
+
     fn store_state_machine_commitment(
         &self,
         height: StateMachineHeight,
@@ -252,12 +484,39 @@ impl IsmpHost for Host {
         Ok(())
     }
 
-    fn freeze_state_machine(&self, height: StateMachineHeight) -> Result<(), Error> {
-        self.frozen_state_machines.borrow_mut().insert(height.id, height);
+    fn freeze_state_machine(
+        &self,
+        height: StateMachineHeight,
+        reason: FreezeReason,
+    ) -> Result<(), Error> {
+        self.frozen_state_machines.borrow_mut().insert(height.id, (height, reason));
+        Ok(())
+    }
+
+    fn unfreeze_state_machine(&self, height: StateMachineHeight) -> Result<(), Error> {
+        self.frozen_state_machines.borrow_mut().remove(&height.id);
         Ok(())
     }
 
-    fn freeze_consensus_client(&self, _client: ConsensusStateId) -> Result<(), Error> {
+    fn is_governance_authority(&self, account: &[u8]) -> bool {
+        account == MOCK_GOVERNANCE_AUTHORITY
+    }
+
+    fn is_consensus_peer(&self, account: &[u8]) -> bool {
+        account == MOCK_CONSENSUS_PEER
+    }
+
+    fn store_byzantine_evidence(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        evidence: Vec<u8>,
+    ) -> Result<(), Error> {
+        self.byzantine_evidence.borrow_mut().insert(consensus_state_id, evidence);
+        Ok(())
+    }
+
+    fn freeze_consensus_client(&self, client: ConsensusStateId) -> Result<(), Error> {
+        self.frozen_consensus_clients.borrow_mut().insert(client);
         Ok(())
     }
 
@@ -266,31 +525,58 @@ impl IsmpHost for Host {
         Ok(())
     }
 
+    fn invalidate_commitments_above(
+        &self,
+        id: StateMachineId,
+        height: u64,
+        finalized_before: Duration,
+    ) -> Result<(), Error> {
+        let update_times = self.state_machine_update_times.borrow();
+        self.state_commitments.borrow_mut().retain(|state_height, _| {
+            state_height.id != id ||
+                state_height.height <= height ||
+                update_times.get(state_height).is_some_and(|time| *time <= finalized_before)
+        });
+        Ok(())
+    }
+
     fn delete_request_commitment(&self, req: &Request) -> Result<(), Error> {
         let hash = hash_request::<Self>(req);
         self.requests.borrow_mut().remove(&hash);
+        self.timed_out.borrow_mut().insert(hash);
         Ok(())
     }
 
     fn store_request_receipt(&self, req: &Request) -> Result<(), Error> {
         let hash = hash_request::<Self>(req);
         self.receipts.borrow_mut().insert(hash, ());
+        self.delivered_requests.borrow_mut().insert((
+            req.source_chain(),
+            req.dest_chain(),
+            req.nonce(),
+        ));
         Ok(())
     }
 
     fn store_response_receipt(&self, res: &Request) -> Result<(), Error> {
         let hash = hash_request::<Self>(res);
         self.receipts.borrow_mut().insert(hash, ());
+        self.responded.borrow_mut().insert(hash);
         Ok(())
     }
 
     fn consensus_client(&self, id: ConsensusClientId) -> Result<Box<dyn ConsensusClient>, Error> {
+        *self.client_lookups.borrow_mut() += 1;
         match id {
             MOCK_CONSENSUS_CLIENT_ID => Ok(Box::new(MockClient)),
             _ => Err(Error::ImplementationSpecific("Client not found".to_string())),
         }
     }
 
+    fn consensus_clients(&self) -> Vec<ConsensusClientId> {
+        vec![MOCK_CONSENSUS_CLIENT_ID]
+    }
+
     fn challenge_period(&self, _consensus_state_id: ConsensusStateId) -> Option<Duration> {
         Some(Duration::from_secs(60 * 60))
     }
@@ -300,23 +586,115 @@ impl IsmpHost for Host {
         _consensus_state_id: ConsensusStateId,
         _period: u64,
     ) -> Result<(), Error> {
-        todo!()
+        Ok(())
+    }
+
+    fn supported_state_machines(&self) -> Vec<StateMachine> {
+        let mut state_machines = vec![self.host_state_machine()];
+        state_machines.extend(self.latest_state_height.borrow().keys().map(|id| id.state_id));
+        state_machines
+    }
+
+    fn is_registered_module(&self, id: &ModuleId) -> bool {
+        id.0 != MOCK_UNKNOWN_MODULE
+    }
+
+    fn requires_response_binding(&self) -> bool {
+        *self.response_binding.borrow()
+    }
+
+    fn allows_anonymous_dispatch(&self) -> bool {
+        *self.anonymous_dispatch.borrow()
+    }
+
+    fn store_last_consensus_proof(&self, consensus_state_id: ConsensusStateId, hash: H256) {
+        self.last_consensus_proofs.borrow_mut().insert(consensus_state_id, hash);
+    }
+
+    fn last_consensus_proof(&self, consensus_state_id: ConsensusStateId) -> Option<H256> {
+        self.last_consensus_proofs.borrow().get(&consensus_state_id).copied()
+    }
+
+    fn optimistic_consensus_updates(&self) -> bool {
+        *self.optimistic_updates.borrow()
+    }
+
+    fn store_pending_consensus_update(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        update: Option<PendingConsensusUpdate>,
+    ) -> Result<(), Error> {
+        let mut pending = self.pending_consensus_updates.borrow_mut();
+        match update {
+            Some(update) => pending.insert(consensus_state_id, update),
+            None => pending.remove(&consensus_state_id),
+        };
+        Ok(())
+    }
+
+    fn pending_consensus_update(
+        &self,
+        consensus_state_id: ConsensusStateId,
+    ) -> Option<PendingConsensusUpdate> {
+        self.pending_consensus_updates.borrow().get(&consensus_state_id).cloned()
+    }
+
+    fn max_get_keys(&self) -> Option<usize> {
+        Some(MOCK_MAX_GET_KEYS)
+    }
+
+    fn trace(&self, event: TraceEvent) {
+        self.traces.borrow_mut().push(event);
+    }
+
+    fn record_delivery(&self, commitment: H256, outcome: DeliveryOutcome) {
+        self.deliveries.borrow_mut().push((commitment, outcome));
+    }
+
+    fn record_proof_metadata(&self, commitment: H256, metadata: ProofMetadata) {
+        self.proof_metadata.borrow_mut().push((commitment, metadata));
+    }
+
+    fn emit_state_machine_unfrozen(&self, event: StateMachineUnfrozen) {
+        self.unfrozen_state_machines.borrow_mut().push(event);
+    }
+
+    fn emit_consensus_ack(&self, ack: ConsensusAckMessage) {
+        self.emitted_acks.borrow_mut().push(ack);
+    }
+
+    fn store_consensus_ack(&self, ack: ConsensusAckMessage) -> Result<(), Error> {
+        self.received_acks.borrow_mut().push(ack);
+        Ok(())
     }
 
     fn allowed_proxies(&self) -> Vec<StateMachine> {
-        todo!()
+        self.allowed_proxies.borrow().clone()
     }
 
-    fn store_allowed_proxies(&self, _allowed: Vec<StateMachine>) {
-        todo!()
+    fn store_allowed_proxies(&self, allowed: Vec<StateMachine>) {
+        *self.allowed_proxies.borrow_mut() = allowed;
     }
 
-    fn unbonding_period(&self, _consensus_state_id: ConsensusStateId) -> Option<Duration> {
-        Some(Duration::from_secs(60 * 60 * 60))
+    fn on_unknown_state_machine(&self) -> UnknownPolicy {
+        *self.unknown_policy.borrow()
+    }
+
+    fn unbonding_period(&self, consensus_state_id: ConsensusStateId) -> Option<Duration> {
+        let period = self
+            .unbonding_periods
+            .borrow()
+            .get(&consensus_state_id)
+            .copied()
+            .unwrap_or(60 * 60 * 60);
+        Some(Duration::from_secs(period))
     }
 
     fn ismp_router(&self) -> Box<dyn IsmpRouter> {
-        Box::new(MockRouter(self.clone()))
+        match self.registry.borrow().clone() {
+            Some(registry) => Box::new(registry),
+            None => Box::new(MockRouter(self.clone())),
+        }
     }
 }
 
@@ -329,20 +707,42 @@ impl Keccak256 for Host {
     }
 }
 
-#[derive(Default)]
-pub struct MockModule;
+pub struct MockModule(pub Host);
 
 impl IsmpModule for MockModule {
-    fn on_accept(&self, _request: Post) -> Result<(), Error> {
+    fn on_accept(&self, request: Post) -> Result<(), Error> {
+        if request.to == MOCK_REJECTING_MODULE {
+            Err(Error::ImplementationSpecific("Request rejected".to_string()))?
+        }
         Ok(())
     }
 
-    fn on_response(&self, _response: Response) -> Result<(), Error> {
+    fn on_response(&self, response: Response) -> Result<(), Error> {
+        self.0.delivered_responses.borrow_mut().push(response);
         Ok(())
     }
 
-    fn on_timeout(&self, _request: Request) -> Result<(), Error> {
-        Ok(())
+    fn on_timeout(&self, _request: Request) -> Result<TimeoutOutcome, Error> {
+        let mut failures = self.0.timeout_failures.borrow_mut();
+        if *failures > 0 {
+            *failures -= 1;
+            Err(Error::ImplementationSpecific("Timeout failed".to_string()))?
+        }
+        let mut retries = self.0.timeout_retries.borrow_mut();
+        if *retries > 0 {
+            *retries -= 1;
+            Err(Error::RetryTimeout)?
+        }
+        let mut unrolled = self.0.unrolled_timeouts.borrow_mut();
+        if *unrolled > 0 {
+            *unrolled -= 1;
+            return Ok(TimeoutOutcome { gas_used: MOCK_TIMEOUT_GAS, rolled_back: false })
+        }
+        Ok(TimeoutOutcome { gas_used: MOCK_TIMEOUT_GAS, rolled_back: true })
+    }
+
+    fn is_authorized_source(&self, caller: &[u8], from: &[u8]) -> bool {
+        caller == from
     }
 }
 
@@ -350,15 +750,16 @@ pub struct MockRouter(pub Host);
 
 impl IsmpRouter for MockRouter {
     fn module_for_id(&self, _bytes: Vec<u8>) -> Result<Box<dyn IsmpModule>, Error> {
-        Ok(Box::new(MockModule))
+        Ok(Box::new(MockModule(self.0.clone())))
     }
 }
 
-pub struct MockDispatcher(pub Arc<Host>);
+pub struct MockDispatcher(pub Rc<Host>);
 
 impl IsmpDispatcher for MockDispatcher {
-    fn dispatch_request(&self, request: DispatchRequest) -> Result<(), Error> {
+    fn dispatch_request(&self, request: DispatchRequest, caller: &[u8]) -> Result<(), Error> {
         let host = self.0.clone();
+        validate_dispatch(host.as_ref(), caller, &request)?;
         let request = match request {
             DispatchRequest::Get(dispatch_get) => {
                 let get = Get {
@@ -383,17 +784,34 @@ impl IsmpDispatcher for MockDispatcher {
                     timeout_timestamp: dispatch_post.timeout_timestamp,
                     data: dispatch_post.data,
                     gas_limit: dispatch_post.gas_limit,
+                    deliver_by: dispatch_post.deliver_by,
+                    version: REQUEST_VERSION,
+                    response_to: dispatch_post.response_to,
+                    meta: dispatch_post.meta,
+                    expects_response: dispatch_post.expects_response,
                 };
                 Request::Post(post)
             }
         };
         let hash = hash_request::<Host>(&request);
         host.requests.borrow_mut().insert(hash);
+        host.trace(TraceEvent {
+            commitment: hash,
+            stage: TraceStage::Dispatched,
+            timestamp: host.timestamp().as_secs(),
+        });
         Ok(())
     }
 
     fn dispatch_response(&self, response: PostResponse) -> Result<(), Error> {
         let host = self.0.clone();
+        if !response.post.expects_response {
+            Err(Error::UnexpectedResponse {
+                nonce: response.post.nonce,
+                source: response.post.dest,
+                dest: response.post.source,
+            })?
+        }
         let response = Response::Post(response);
         let hash = hash_response::<Host>(&response);
         if host.responses.borrow().contains(&hash) {
@@ -0,0 +1,668 @@
+// Copyright (C) Polytope Labs Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A host wrapper that buffers mutations so they can be inspected, rolled back or committed
+
+use ismp::{
+    consensus::{
+        ConsensusClient, ConsensusClientId, ConsensusStateId, PendingConsensusUpdate,
+        ProofMetadata, StateCommitment, StateMachineHeight, StateMachineId,
+    },
+    error::Error,
+    events::{DeliveryOutcome, StateMachineUnfrozen, TraceEvent},
+    host::{FreezeReason, IsmpHost, RequestStatus, StateMachine, UnknownPolicy},
+    messaging::ConsensusAckMessage,
+    module::ModuleId,
+    router::{IsmpRouter, Request},
+    util::{compact_nonces, hash_request, Keccak256},
+};
+use primitive_types::H256;
+use std::{cell::RefCell, ops::RangeInclusive, time::Duration};
+
+/// A mutating call made against a [`SnapshotHost`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mutation {
+    /// [`IsmpHost::store_consensus_state_id`]
+    ConsensusStateId(ConsensusStateId, ConsensusClientId),
+    /// [`IsmpHost::store_consensus_state`]
+    ConsensusState(ConsensusStateId, Vec<u8>),
+    /// [`IsmpHost::store_unbonding_period`]
+    UnbondingPeriod(ConsensusStateId, u64),
+    /// [`IsmpHost::store_pending_unbonding_period`]
+    PendingUnbondingPeriod(ConsensusStateId, Option<u64>),
+    /// [`IsmpHost::store_consensus_update_time`]
+    ConsensusUpdateTime(ConsensusStateId, Duration),
+    /// [`IsmpHost::store_state_machine_update_time`]
+    StateMachineUpdateTime(StateMachineHeight, Duration),
+    /// [`IsmpHost::store_state_machine_commitment`]
+    StateMachineCommitment(StateMachineHeight, StateCommitment),
+    /// [`IsmpHost::freeze_state_machine`]
+    FreezeStateMachine(StateMachineHeight, FreezeReason),
+    /// [`IsmpHost::unfreeze_state_machine`]
+    UnfreezeStateMachine(StateMachineHeight),
+    /// [`IsmpHost::freeze_consensus_client`]
+    FreezeConsensusClient(ConsensusStateId),
+    /// [`IsmpHost::store_byzantine_evidence`]
+    ByzantineEvidence(ConsensusStateId, Vec<u8>),
+    /// [`IsmpHost::store_latest_commitment_height`]
+    LatestCommitmentHeight(StateMachineHeight),
+    /// [`IsmpHost::invalidate_commitments_above`]
+    InvalidateCommitmentsAbove(StateMachineId, u64, Duration),
+    /// [`IsmpHost::delete_request_commitment`]
+    DeleteRequestCommitment(Request),
+    /// [`IsmpHost::store_request_receipt`]
+    RequestReceipt(Request),
+    /// [`IsmpHost::store_response_receipt`]
+    ResponseReceipt(Request),
+    /// [`IsmpHost::store_challenge_period`]
+    ChallengePeriod(ConsensusStateId, u64),
+    /// [`IsmpHost::store_allowed_proxies`]
+    AllowedProxies(Vec<StateMachine>),
+    /// [`IsmpHost::store_pending_consensus_update`]
+    PendingConsensusUpdate(ConsensusStateId, Option<PendingConsensusUpdate>),
+    /// [`IsmpHost::store_last_consensus_proof`]
+    LastConsensusProof(ConsensusStateId, H256),
+    /// [`IsmpHost::record_delivery`]
+    Delivery(H256, DeliveryOutcome),
+    /// [`IsmpHost::store_consensus_ack`]
+    ConsensusAck(ConsensusAckMessage),
+}
+
+/// Wraps a host and records every mutating call instead of applying it. Reads observe the
+/// recorded mutations on top of the underlying host, so handlers behave as they would against the
+/// host itself. The recorded mutations can then be inspected and either rolled back or committed
+/// to the underlying host.
+///
+/// Modules returned by [`IsmpHost::ismp_router`] act on the underlying host directly and their
+/// side effects are not recorded, neither are those of [`IsmpHost::drain_elapsed_commitments`]
+/// and [`IsmpHost::emit_consensus_ack`] which hosts call outside of message handling.
+pub struct SnapshotHost<H> {
+    host: H,
+    mutations: RefCell<Vec<Mutation>>,
+}
+
+impl<H: IsmpHost> SnapshotHost<H> {
+    /// Wrap a host
+    pub fn new(host: H) -> Self {
+        Self { host, mutations: Default::default() }
+    }
+
+    /// Returns the mutations recorded since the last commit or rollback, in the order they were
+    /// made.
+    pub fn mutations(&self) -> Vec<Mutation> {
+        self.mutations.borrow().clone()
+    }
+
+    /// Discard all recorded mutations
+    pub fn rollback(&self) {
+        self.mutations.borrow_mut().clear();
+    }
+
+    /// Apply all recorded mutations to the underlying host in the order they were made
+    pub fn commit(&self) -> Result<(), Error> {
+        for mutation in self.mutations.take() {
+            match mutation {
+                Mutation::ConsensusStateId(id, client_id) => {
+                    self.host.store_consensus_state_id(id, client_id)?
+                }
+                Mutation::ConsensusState(id, state) => {
+                    self.host.store_consensus_state(id, state)?
+                }
+                Mutation::UnbondingPeriod(id, period) => {
+                    self.host.store_unbonding_period(id, period)?
+                }
+                Mutation::PendingUnbondingPeriod(id, period) => {
+                    self.host.store_pending_unbonding_period(id, period)?
+                }
+                Mutation::ConsensusUpdateTime(id, time) => {
+                    self.host.store_consensus_update_time(id, time)?
+                }
+                Mutation::StateMachineUpdateTime(height, time) => {
+                    self.host.store_state_machine_update_time(height, time)?
+                }
+                Mutation::StateMachineCommitment(height, commitment) => {
+                    self.host.store_state_machine_commitment(height, commitment)?
+                }
+                Mutation::FreezeStateMachine(height, reason) => {
+                    self.host.freeze_state_machine(height, reason)?
+                }
+                Mutation::UnfreezeStateMachine(height) => {
+                    self.host.unfreeze_state_machine(height)?
+                }
+                Mutation::FreezeConsensusClient(id) => self.host.freeze_consensus_client(id)?,
+                Mutation::ByzantineEvidence(id, evidence) => {
+                    self.host.store_byzantine_evidence(id, evidence)?
+                }
+                Mutation::LatestCommitmentHeight(height) => {
+                    self.host.store_latest_commitment_height(height)?
+                }
+                Mutation::InvalidateCommitmentsAbove(id, height, finalized_before) => {
+                    self.host.invalidate_commitments_above(id, height, finalized_before)?
+                }
+                Mutation::DeleteRequestCommitment(req) => {
+                    self.host.delete_request_commitment(&req)?
+                }
+                Mutation::RequestReceipt(req) => self.host.store_request_receipt(&req)?,
+                Mutation::ResponseReceipt(req) => self.host.store_response_receipt(&req)?,
+                Mutation::ChallengePeriod(id, period) => {
+                    self.host.store_challenge_period(id, period)?
+                }
+                Mutation::AllowedProxies(allowed) => self.host.store_allowed_proxies(allowed),
+                Mutation::PendingConsensusUpdate(id, update) => {
+                    self.host.store_pending_consensus_update(id, update)?
+                }
+                Mutation::LastConsensusProof(id, hash) => {
+                    self.host.store_last_consensus_proof(id, hash)
+                }
+                Mutation::Delivery(commitment, outcome) => {
+                    self.host.record_delivery(commitment, outcome)
+                }
+                Mutation::ConsensusAck(ack) => self.host.store_consensus_ack(ack)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the underlying host, discarding any recorded mutations
+    pub fn into_inner(self) -> H {
+        self.host
+    }
+
+    fn record(&self, mutation: Mutation) -> Result<(), Error> {
+        self.mutations.borrow_mut().push(mutation);
+        Ok(())
+    }
+
+    /// Returns the result of the most recent mutation matched by `f`
+    fn latest<T>(&self, f: impl FnMut(&Mutation) -> Option<T>) -> Option<T> {
+        self.mutations.borrow().iter().rev().find_map(f)
+    }
+}
+
+impl<H: IsmpHost> Keccak256 for SnapshotHost<H> {
+    fn keccak256(bytes: &[u8]) -> H256
+    where
+        Self: Sized,
+    {
+        H::keccak256(bytes)
+    }
+}
+
+impl<H: IsmpHost> IsmpHost for SnapshotHost<H> {
+    fn host_state_machine(&self) -> StateMachine {
+        self.host.host_state_machine()
+    }
+
+    fn latest_commitment_height(&self, id: StateMachineId) -> Result<u64, Error> {
+        self.latest(|mutation| match mutation {
+            Mutation::LatestCommitmentHeight(height) if height.id == id => Some(height.height),
+            _ => None,
+        })
+        .map_or_else(|| self.host.latest_commitment_height(id), Ok)
+    }
+
+    fn state_machine_commitment(
+        &self,
+        height: StateMachineHeight,
+    ) -> Result<StateCommitment, Error> {
+        self.latest(|mutation| match mutation {
+            Mutation::StateMachineCommitment(stored, commitment) if *stored == height => {
+                Some(Ok(*commitment))
+            }
+            Mutation::InvalidateCommitmentsAbove(id, above, finalized_before)
+                if *id == height.id &&
+                    height.height > *above &&
+                    self.state_machine_update_time(height)
+                        .is_ok_and(|time| time > *finalized_before) =>
+            {
+                Some(Err(Error::StateCommitmentNotFound { height }))
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| self.host.state_machine_commitment(height))
+    }
+
+    fn consensus_update_time(
+        &self,
+        consensus_state_id: ConsensusStateId,
+    ) -> Result<Duration, Error> {
+        self.latest(|mutation| match mutation {
+            Mutation::ConsensusUpdateTime(id, time) if *id == consensus_state_id => Some(*time),
+            _ => None,
+        })
+        .map_or_else(|| self.host.consensus_update_time(consensus_state_id), Ok)
+    }
+
+    fn state_machine_update_time(
+        &self,
+        state_machine_height: StateMachineHeight,
+    ) -> Result<Duration, Error> {
+        self.latest(|mutation| match mutation {
+            Mutation::StateMachineUpdateTime(height, time) if *height == state_machine_height => {
+                Some(*time)
+            }
+            _ => None,
+        })
+        .map_or_else(|| self.host.state_machine_update_time(state_machine_height), Ok)
+    }
+
+    fn consensus_client_id(
+        &self,
+        consensus_state_id: ConsensusStateId,
+    ) -> Option<ConsensusClientId> {
+        self.latest(|mutation| match mutation {
+            Mutation::ConsensusStateId(id, client_id) if *id == consensus_state_id => {
+                Some(*client_id)
+            }
+            _ => None,
+        })
+        .or_else(|| self.host.consensus_client_id(consensus_state_id))
+    }
+
+    fn consensus_state(&self, consensus_state_id: ConsensusStateId) -> Result<Vec<u8>, Error> {
+        self.latest(|mutation| match mutation {
+            Mutation::ConsensusState(id, state) if *id == consensus_state_id => Some(state.clone()),
+            _ => None,
+        })
+        .map_or_else(|| self.host.consensus_state(consensus_state_id), Ok)
+    }
+
+    fn timestamp(&self) -> Duration {
+        self.host.timestamp()
+    }
+
+    fn is_state_machine_frozen(&self, machine: StateMachineHeight) -> Result<(), Error> {
+        let frozen = self.latest(|mutation| match mutation {
+            Mutation::FreezeStateMachine(height, reason) if height.id == machine.id => {
+                Some((machine.height >= height.height).then_some(*reason))
+            }
+            Mutation::UnfreezeStateMachine(height) if height.id == machine.id => Some(None),
+            _ => None,
+        });
+        match frozen {
+            Some(Some(reason)) => Err(Error::FrozenStateMachine { height: machine, reason }),
+            Some(None) => Ok(()),
+            None => self.host.is_state_machine_frozen(machine),
+        }
+    }
+
+    fn is_consensus_client_frozen(
+        &self,
+        consensus_state_id: ConsensusStateId,
+    ) -> Result<(), Error> {
+        let frozen = self.latest(|mutation| match mutation {
+            Mutation::FreezeConsensusClient(id) if *id == consensus_state_id => Some(()),
+            _ => None,
+        });
+        if frozen.is_some() {
+            Err(Error::FrozenConsensusClient { consensus_state_id })?
+        }
+
+        self.host.is_consensus_client_frozen(consensus_state_id)
+    }
+
+    fn request_commitment(&self, req: H256) -> Result<(), Error> {
+        let deleted = self.latest(|mutation| match mutation {
+            Mutation::DeleteRequestCommitment(request) => {
+                Some(request.clone()).filter(|request| hash_request::<H>(request) == req)
+            }
+            _ => None,
+        });
+        if let Some(request) = deleted {
+            Err(Error::RequestCommitmentNotFound {
+                nonce: request.nonce(),
+                source: request.source_chain(),
+                dest: request.dest_chain(),
+            })?
+        }
+
+        self.host.request_commitment(req)
+    }
+
+    fn next_nonce(&self) -> u64 {
+        self.host.next_nonce()
+    }
+
+    fn request_receipt(&self, req: &Request) -> Option<()> {
+        self.latest(|mutation| match mutation {
+            Mutation::RequestReceipt(request) if request.commitment_eq::<H>(req) => Some(()),
+            _ => None,
+        })
+        .or_else(|| self.host.request_receipt(req))
+    }
+
+    fn response_receipt(&self, res: &Request) -> Option<()> {
+        self.latest(|mutation| match mutation {
+            Mutation::ResponseReceipt(request) if request.commitment_eq::<H>(res) => Some(()),
+            _ => None,
+        })
+        .or_else(|| self.host.response_receipt(res))
+    }
+
+    fn delivered_nonces(
+        &self,
+        source: StateMachine,
+        dest: StateMachine,
+    ) -> Vec<RangeInclusive<u64>> {
+        let recorded = self
+            .mutations
+            .borrow()
+            .iter()
+            .filter_map(|mutation| match mutation {
+                Mutation::RequestReceipt(req)
+                    if req.source_chain() == source && req.dest_chain() == dest =>
+                {
+                    Some(req.nonce())
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let delivered = self.host.delivered_nonces(source, dest);
+        if recorded.is_empty() {
+            return delivered
+        }
+        compact_nonces(delivered.into_iter().flatten().chain(recorded))
+    }
+
+    fn request_status(&self, commitment: H256) -> RequestStatus {
+        let is_request = |req: &Request| hash_request::<H>(req) == commitment;
+        self.latest(|mutation| match mutation {
+            Mutation::ResponseReceipt(req) if is_request(req) => Some(RequestStatus::Responded),
+            Mutation::DeleteRequestCommitment(req) if is_request(req) => {
+                Some(RequestStatus::TimedOut)
+            }
+            Mutation::RequestReceipt(req) if is_request(req) => Some(RequestStatus::Delivered),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.host.request_status(commitment))
+    }
+
+    fn store_consensus_state_id(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        client_id: ConsensusClientId,
+    ) -> Result<(), Error> {
+        self.record(Mutation::ConsensusStateId(consensus_state_id, client_id))
+    }
+
+    fn store_consensus_state(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        consensus_state: Vec<u8>,
+    ) -> Result<(), Error> {
+        self.record(Mutation::ConsensusState(consensus_state_id, consensus_state))
+    }
+
+    fn store_unbonding_period(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        period: u64,
+    ) -> Result<(), Error> {
+        self.record(Mutation::UnbondingPeriod(consensus_state_id, period))
+    }
+
+    fn store_pending_unbonding_period(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        period: Option<u64>,
+    ) -> Result<(), Error> {
+        self.record(Mutation::PendingUnbondingPeriod(consensus_state_id, period))
+    }
+
+    fn pending_unbonding_period(&self, consensus_state_id: ConsensusStateId) -> Option<u64> {
+        self.latest(|mutation| match mutation {
+            Mutation::PendingUnbondingPeriod(id, period) if *id == consensus_state_id => {
+                Some(*period)
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| self.host.pending_unbonding_period(consensus_state_id))
+    }
+
+    fn store_consensus_update_time(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        timestamp: Duration,
+    ) -> Result<(), Error> {
+        self.record(Mutation::ConsensusUpdateTime(consensus_state_id, timestamp))
+    }
+
+    fn store_state_machine_update_time(
+        &self,
+        state_machine_height: StateMachineHeight,
+        timestamp: Duration,
+    ) -> Result<(), Error> {
+        self.record(Mutation::StateMachineUpdateTime(state_machine_height, timestamp))
+    }
+
+    fn drain_elapsed_commitments(&self, now: Duration) -> Vec<StateMachineHeight> {
+        self.host.drain_elapsed_commitments(now)
+    }
+
+    fn store_state_machine_commitment(
+        &self,
+        height: StateMachineHeight,
+        state: StateCommitment,
+    ) -> Result<(), Error> {
+        self.record(Mutation::StateMachineCommitment(height, state))
+    }
+
+    fn freeze_state_machine(
+        &self,
+        height: StateMachineHeight,
+        reason: FreezeReason,
+    ) -> Result<(), Error> {
+        self.record(Mutation::FreezeStateMachine(height, reason))
+    }
+
+    fn unfreeze_state_machine(&self, height: StateMachineHeight) -> Result<(), Error> {
+        self.record(Mutation::UnfreezeStateMachine(height))
+    }
+
+    fn is_governance_authority(&self, account: &[u8]) -> bool {
+        self.host.is_governance_authority(account)
+    }
+
+    fn is_consensus_peer(&self, account: &[u8]) -> bool {
+        self.host.is_consensus_peer(account)
+    }
+
+    fn freeze_consensus_client(&self, consensus_state_id: ConsensusStateId) -> Result<(), Error> {
+        self.record(Mutation::FreezeConsensusClient(consensus_state_id))
+    }
+
+    fn store_byzantine_evidence(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        evidence: Vec<u8>,
+    ) -> Result<(), Error> {
+        self.record(Mutation::ByzantineEvidence(consensus_state_id, evidence))
+    }
+
+    fn store_latest_commitment_height(&self, height: StateMachineHeight) -> Result<(), Error> {
+        self.record(Mutation::LatestCommitmentHeight(height))
+    }
+
+    fn invalidate_commitments_above(
+        &self,
+        id: StateMachineId,
+        height: u64,
+        finalized_before: Duration,
+    ) -> Result<(), Error> {
+        self.record(Mutation::InvalidateCommitmentsAbove(id, height, finalized_before))
+    }
+
+    fn delete_request_commitment(&self, req: &Request) -> Result<(), Error> {
+        self.record(Mutation::DeleteRequestCommitment(req.clone()))
+    }
+
+    fn store_request_receipt(&self, req: &Request) -> Result<(), Error> {
+        self.record(Mutation::RequestReceipt(req.clone()))
+    }
+
+    fn store_response_receipt(&self, req: &Request) -> Result<(), Error> {
+        self.record(Mutation::ResponseReceipt(req.clone()))
+    }
+
+    fn consensus_client(&self, id: ConsensusClientId) -> Result<Box<dyn ConsensusClient>, Error> {
+        self.host.consensus_client(id)
+    }
+
+    fn consensus_clients(&self) -> Vec<ConsensusClientId> {
+        self.host.consensus_clients()
+    }
+
+    fn challenge_period(&self, consensus_state_id: ConsensusStateId) -> Option<Duration> {
+        self.latest(|mutation| match mutation {
+            Mutation::ChallengePeriod(id, period) if *id == consensus_state_id => {
+                Some(Duration::from_secs(*period))
+            }
+            _ => None,
+        })
+        .or_else(|| self.host.challenge_period(consensus_state_id))
+    }
+
+    fn store_challenge_period(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        period: u64,
+    ) -> Result<(), Error> {
+        self.record(Mutation::ChallengePeriod(consensus_state_id, period))
+    }
+
+    fn is_client_type_allowed(&self, id: ConsensusClientId) -> bool {
+        self.host.is_client_type_allowed(id)
+    }
+
+    fn allowed_proxies(&self) -> Vec<StateMachine> {
+        self.latest(|mutation| match mutation {
+            Mutation::AllowedProxies(allowed) => Some(allowed.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| self.host.allowed_proxies())
+    }
+
+    fn store_allowed_proxies(&self, allowed: Vec<StateMachine>) {
+        let _ = self.record(Mutation::AllowedProxies(allowed));
+    }
+
+    fn supported_state_machines(&self) -> Vec<StateMachine> {
+        self.host.supported_state_machines()
+    }
+
+    fn is_registered_module(&self, id: &ModuleId) -> bool {
+        self.host.is_registered_module(id)
+    }
+
+    fn requires_response_binding(&self) -> bool {
+        self.host.requires_response_binding()
+    }
+
+    fn allows_anonymous_dispatch(&self) -> bool {
+        self.host.allows_anonymous_dispatch()
+    }
+
+    fn cache_verified_proof(&self, proof_hash: H256) {
+        self.host.cache_verified_proof(proof_hash)
+    }
+
+    fn is_proof_verified(&self, proof_hash: H256) -> bool {
+        self.host.is_proof_verified(proof_hash)
+    }
+
+    fn store_last_consensus_proof(&self, consensus_state_id: ConsensusStateId, hash: H256) {
+        let _ = self.record(Mutation::LastConsensusProof(consensus_state_id, hash));
+    }
+
+    fn last_consensus_proof(&self, consensus_state_id: ConsensusStateId) -> Option<H256> {
+        self.latest(|mutation| match mutation {
+            Mutation::LastConsensusProof(id, hash) if *id == consensus_state_id => Some(*hash),
+            _ => None,
+        })
+        .or_else(|| self.host.last_consensus_proof(consensus_state_id))
+    }
+
+    fn optimistic_consensus_updates(&self) -> bool {
+        self.host.optimistic_consensus_updates()
+    }
+
+    fn store_pending_consensus_update(
+        &self,
+        consensus_state_id: ConsensusStateId,
+        update: Option<PendingConsensusUpdate>,
+    ) -> Result<(), Error> {
+        self.record(Mutation::PendingConsensusUpdate(consensus_state_id, update))
+    }
+
+    fn pending_consensus_update(
+        &self,
+        consensus_state_id: ConsensusStateId,
+    ) -> Option<PendingConsensusUpdate> {
+        self.latest(|mutation| match mutation {
+            Mutation::PendingConsensusUpdate(id, update) if *id == consensus_state_id => {
+                Some(update.clone())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| self.host.pending_consensus_update(consensus_state_id))
+    }
+
+    fn max_get_keys(&self) -> Option<usize> {
+        self.host.max_get_keys()
+    }
+
+    fn on_unknown_state_machine(&self) -> UnknownPolicy {
+        self.host.on_unknown_state_machine()
+    }
+
+    fn trace(&self, event: TraceEvent) {
+        self.host.trace(event)
+    }
+
+    fn record_delivery(&self, commitment: H256, outcome: DeliveryOutcome) {
+        let _ = self.record(Mutation::Delivery(commitment, outcome));
+    }
+
+    fn record_proof_metadata(&self, commitment: H256, metadata: ProofMetadata) {
+        self.host.record_proof_metadata(commitment, metadata)
+    }
+
+    fn emit_state_machine_unfrozen(&self, event: StateMachineUnfrozen) {
+        self.host.emit_state_machine_unfrozen(event)
+    }
+
+    fn emit_consensus_ack(&self, ack: ConsensusAckMessage) {
+        self.host.emit_consensus_ack(ack)
+    }
+
+    fn store_consensus_ack(&self, ack: ConsensusAckMessage) -> Result<(), Error> {
+        self.record(Mutation::ConsensusAck(ack))
+    }
+
+    fn unbonding_period(&self, consensus_state_id: ConsensusStateId) -> Option<Duration> {
+        self.latest(|mutation| match mutation {
+            Mutation::UnbondingPeriod(id, period) if *id == consensus_state_id => {
+                Some(Duration::from_secs(*period))
+            }
+            _ => None,
+        })
+        .or_else(|| self.host.unbonding_period(consensus_state_id))
+    }
+
+    fn ismp_router(&self) -> Box<dyn IsmpRouter> {
+        self.host.ismp_router()
+    }
+}
@@ -1,13 +1,44 @@
 use crate::{
-    check_challenge_period, check_client_expiry, frozen_check,
-    mocks::{Host, MockDispatcher},
-    timeout_post_processing_check, write_outgoing_commitments,
+    byzantine_update_check, challenge_period_elapsed_check, check_challenge_period,
+    check_client_expiry, check_consecutive_updates, check_no_replay_after_update,
+    client_lookup_check, commitment_timestamp_check, consensus_ack_check,
+    consensus_not_advanced_check, consensus_update_determinism_check, delivered_nonces_check,
+    delivery_outcome_check, drain_elapsed_commitments_check, dry_run_verify_check,
+    duplicate_consensus_proof_check, duplicate_consensus_state_id_check, early_timeout_check,
+    empty_dispatch_fields_check, empty_message_check, expected_trusted_state_check,
+    fire_and_forget_check, first_commitment_check, frozen_check, frozen_consensus_client_check,
+    get_read_height_finalized_check, get_response_delivery_check, heterogeneous_batch_check,
+    housekeeping_update_check, late_delivery_check, malformed_create_client_check,
+    max_get_keys_check, missing_get_values_check, mock_post,
+    mocks::{Host, MockClient, MockDispatcher, MockModule, MOCK_GOVERNANCE_AUTHORITY},
+    non_monotonic_clock_check, optimistic_update_check, proof_version_check,
+    protocol_version_check, pruned_height_check, request_metadata_check, request_status_check,
+    request_trace_check, response_binding_check, rollback_check, self_dispatch_check,
+    snapshot::SnapshotHost,
+    snapshot_check, timeout_batch_check, timeout_post_processing_check, timeout_retry_check,
+    unauthorized_source_check, unbonding_period_rotation_check, unexpected_client_type_check,
+    unfreeze_check, unknown_module_check, unknown_state_machine_policy_check,
+    verify_get_response_check, write_outgoing_commitments, wrong_destination_check,
 };
-use std::sync::Arc;
+use alloy_primitives::{keccak256, B256, U256};
+use alloy_trie::{proof::ProofRetainer, HashBuilder, Nibbles, TrieAccount, EMPTY_ROOT_HASH};
+use ismp::{
+    consensus::{ConsensusClient, StateProofResult, BASE_VERIFY_COST, VERIFY_COST_PER_BYTE},
+    error::Error,
+    evm_proof::{verify_account_and_storage, EvmProof},
+    host::{IsmpHost, StateMachine},
+    module::{ModuleId, ModuleRegistry},
+    router::{Get, IsmpRouter, Post, PostResponse, Request, Response},
+    substrate_proof::{verify_substrate_state_proof, Blake2Hasher, SubstrateLayout},
+    util::{hash_request, hash_request_keccak},
+};
+use primitive_types::{H160, H256};
+use sp_trie::{read_trie_value, recorder::Recorder, MemoryDB, TrieDBMutBuilder, TrieMut};
+use std::{rc::Rc, time::Duration};
 
 #[test]
 fn dispatcher_should_write_receipts_for_outgoing_requests_and_responses() {
-    let host = Arc::new(Host::default());
+    let host = Rc::new(Host::default());
     let dispatcher = MockDispatcher(host.clone());
     write_outgoing_commitments(&*host, &dispatcher).unwrap();
 }
@@ -18,20 +49,622 @@ fn should_reject_updates_within_challenge_period() {
     check_challenge_period(&host).unwrap()
 }
 
+#[test]
+fn should_reject_updates_while_previous_update_is_pending() {
+    let host = Host::default();
+    check_consecutive_updates(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_replayed_updates_after_a_successful_update() {
+    let host = Host::default();
+    check_no_replay_after_update(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_duplicate_consensus_state_ids() {
+    let host = Host::default();
+    duplicate_consensus_state_id_check(&host).unwrap()
+}
+
+#[test]
+fn should_reject_proofs_at_pruned_heights() {
+    let host = Host::default();
+    pruned_height_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_malformed_create_client_messages() {
+    let host = Host::default();
+    malformed_create_client_check(&host).unwrap()
+}
+
+#[test]
+fn should_reject_unexpected_client_types() {
+    let host = Host::default();
+    unexpected_client_type_check(&host).unwrap()
+}
+
+#[test]
+fn should_reject_updates_against_stale_trusted_state() {
+    let host = Host::default();
+    expected_trusted_state_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_updates_with_non_monotonic_clock() {
+    let host = Host::default();
+    non_monotonic_clock_check(&host).unwrap()
+}
+
+#[test]
+fn should_reject_decreasing_commitment_timestamps() {
+    let host = Host::default();
+    commitment_timestamp_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_accept_first_commitments_for_new_state_machines() {
+    let host = Host::default();
+    first_commitment_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_accept_consensus_updates_without_commitments() {
+    let host = Host::default();
+    housekeeping_update_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_accept_updates_once_the_challenge_period_elapses() {
+    let host = Host::default();
+    challenge_period_elapsed_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_consensus_updates_that_do_not_advance() {
+    let host = Host::default();
+    consensus_not_advanced_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_incompatible_protocol_versions() {
+    let host = Host::default();
+    protocol_version_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_duplicate_consensus_proofs() {
+    let host = Host::default();
+    duplicate_consensus_proof_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_invalidate_commitments_on_rollback() {
+    let host = Host::default();
+    rollback_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_apply_unbonding_period_changes_prospectively() {
+    let host = Host::default();
+    unbonding_period_rotation_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_process_consensus_updates_deterministically() {
+    let host_1 = Host::default();
+    let host_2 = Host::default();
+    consensus_update_determinism_check((&host_1, &host_1.clock), (&host_2, &host_2.clock)).unwrap()
+}
+
+#[test]
+fn should_finalize_heights_once_their_challenge_period_elapses() {
+    let host = Host::default();
+    drain_elapsed_commitments_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_freeze_clients_on_byzantine_updates() {
+    let host = Host::default();
+    byzantine_update_check(&host).unwrap()
+}
+
+#[test]
+fn should_dry_run_consensus_proofs_without_side_effects() {
+    let host = Host::default();
+    dry_run_verify_check(&host).unwrap()
+}
+
+#[test]
+fn should_promote_optimistic_updates_after_their_challenge_period() {
+    let host = Host::default();
+    optimistic_update_check(&host).unwrap()
+}
+
+#[test]
+fn should_acknowledge_finalized_heights_to_peers() {
+    let host = Host::default();
+    let peer = Host::default();
+    consensus_ack_check(&host, &peer).unwrap()
+}
+
+#[test]
+fn snapshot_host_should_rollback_and_commit_mutations() {
+    let host = Host::default();
+    snapshot_check(&host).unwrap()
+}
+
 #[test]
 fn should_reject_messages_for_frozen_state_machines() {
     let host = Host::default();
     frozen_check(&host).unwrap()
 }
 
+#[test]
+fn should_unfreeze_state_machines_through_governance() {
+    let host = Host::default();
+    unfreeze_check(&host, &host.clock).unwrap();
+    // Every successful unfreeze is emitted
+    let unfrozen = host.unfrozen_state_machines.borrow();
+    assert_eq!(unfrozen.len(), 2);
+    assert!(unfrozen.iter().all(|event| event.authority == MOCK_GOVERNANCE_AUTHORITY));
+}
+
+#[test]
+fn should_reject_messages_for_frozen_consensus_clients() {
+    let host = Host::default();
+    frozen_consensus_client_check(&host, &host.clock).unwrap()
+}
+
 #[test]
 fn should_reject_expired_check_clients() {
     let host = Host::default();
     check_client_expiry(&host).unwrap()
 }
+
+#[test]
+fn should_flag_late_deliveries() {
+    let host = Host::default();
+    late_delivery_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_requests_to_unknown_modules() {
+    let host = Host::default();
+    unknown_module_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_unsupported_proof_versions() {
+    let host = Host::default();
+    proof_version_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_report_delivered_nonces_as_ranges() {
+    let host = Host::default();
+    delivered_nonces_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_requests_for_other_destinations() {
+    let host = Host::default();
+    wrong_destination_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_reject_heterogeneous_request_batches() {
+    let host = Host::default();
+    heterogeneous_batch_check(&host, &host.clock).unwrap()
+}
+
+#[test]
+fn should_apply_the_unknown_state_machine_policy() {
+    let host = Host::default();
+    unknown_state_machine_policy_check(&host).unwrap()
+}
+
+#[test]
+fn should_record_the_outcome_of_every_delivery() {
+    let host = Host::default();
+    delivery_outcome_check(&host).unwrap()
+}
+
+#[test]
+fn should_look_up_each_consensus_client_once_per_batch() {
+    let host = Host::default();
+    client_lookup_check(&host).unwrap()
+}
+
+#[test]
+fn should_reject_empty_messages() {
+    let host = Host::default();
+    empty_message_check(&host).unwrap()
+}
+
+#[test]
+fn should_only_accept_get_responses_for_finalized_heights() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    get_read_height_finalized_check(&*host, &dispatcher, &host.clock).unwrap()
+}
+
+#[test]
+fn should_bound_get_request_keys() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    max_get_keys_check(&*host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_verify_get_responses_without_dispatching() {
+    let host = Host::default();
+    verify_get_response_check(&host, &host.clock).unwrap();
+    assert!(host.delivered_responses.borrow().is_empty());
+}
+
+#[test]
+fn should_reject_proofs_missing_get_values() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    missing_get_values_check(&*host, &dispatcher, &host.clock).unwrap();
+    assert!(host.delivered_responses.borrow().is_empty());
+}
+
+#[test]
+fn should_deliver_present_and_absent_get_values() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    get_response_delivery_check(&host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_only_deliver_responses_bound_to_received_requests() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    response_binding_check(&host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_reject_responses_to_fire_and_forget_requests() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    fire_and_forget_check(&host, &dispatcher).unwrap()
+}
+
 #[test]
 fn should_process_timeouts_correctly() {
-    let host = Arc::new(Host::default());
+    let host = Rc::new(Host::default());
     let dispatcher = MockDispatcher(host.clone());
     timeout_post_processing_check(&*host, &dispatcher).unwrap()
 }
+
+#[test]
+fn should_process_timeout_batches_per_request() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    timeout_batch_check(&*host, &dispatcher, &host.clock).unwrap()
+}
+
+#[test]
+fn should_distinguish_early_timeouts_from_failed_proofs() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    early_timeout_check(&*host, &dispatcher, &host.clock).unwrap()
+}
+
+#[test]
+fn should_retain_commitments_for_retried_timeouts() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    timeout_retry_check(&host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_trace_request_lifecycle() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    request_trace_check(&host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_report_request_status_by_commitment() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    request_status_check(&host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_reject_self_dispatched_requests() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    self_dispatch_check(&*host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_reject_requests_from_unauthorized_sources() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    unauthorized_source_check(&*host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_validate_empty_dispatch_fields() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    empty_dispatch_fields_check(&host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_commit_and_limit_request_metadata() {
+    let host = Rc::new(Host::default());
+    let dispatcher = MockDispatcher(host.clone());
+    request_metadata_check(&*host, &dispatcher).unwrap()
+}
+
+#[test]
+fn should_route_responses_to_the_callback_module() {
+    let post = Post {
+        from: vec![1u8; 32],
+        to: vec![2u8; 32],
+        ..mock_post(StateMachine::Kusama(2000), StateMachine::Kusama(2001), 0)
+    };
+    let callback = Post { response_to: Some(vec![3u8; 32]), ..post.clone() };
+
+    // The callback module is committed to, so it can't be altered in transit
+    assert_ne!(
+        hash_request::<Host>(&Request::Post(post.clone())),
+        hash_request::<Host>(&Request::Post(callback.clone()))
+    );
+    assert_eq!(Response::Post(post.respond(vec![])).destination_module(), vec![1u8; 32]);
+    assert_eq!(Response::Post(callback.respond(vec![])).destination_module(), vec![3u8; 32]);
+}
+
+#[test]
+fn module_registry_should_resolve_pallet_and_fixed_ids() {
+    let host = Host::default();
+    let mut registry = ModuleRegistry::default();
+    let fixed = H256::repeat_byte(7);
+    registry.register(5u8, Rc::new(MockModule(host.clone()))).unwrap();
+    registry.register(fixed, Rc::new(MockModule(host.clone()))).unwrap();
+
+    let pallet = ModuleId::from(vec![5u8]);
+    assert_eq!(pallet, ModuleId::pallet(5));
+    assert_eq!(pallet.as_pallet_index(), Some(5));
+    assert_eq!(ModuleId::from(fixed.0).as_fixed(), Some(fixed.0));
+    assert!(registry.contains(&pallet));
+    assert!(registry.contains(&ModuleId::from(fixed.0)));
+
+    let res = registry.register(ModuleId::pallet(5), Rc::new(MockModule(host.clone())));
+    assert!(matches!(res, Err(Error::DuplicateModule { id }) if id == pallet));
+
+    let response = Response::Post(PostResponse {
+        post: Post {
+            to: vec![5u8],
+            data: vec![],
+            ..mock_post(StateMachine::Kusama(2000), host.host_state_machine(), 0)
+        },
+        response: vec![],
+    });
+    registry.module_for_id(vec![5u8]).unwrap().on_response(response.clone()).unwrap();
+    assert_eq!(*host.delivered_responses.borrow(), vec![response]);
+
+    let res = registry.module_for_id(vec![6u8]);
+    assert!(matches!(res, Err(Error::UnknownModule { id }) if id == ModuleId::pallet(6)));
+}
+
+#[test]
+fn default_verify_cost_grows_with_the_proof() {
+    assert_eq!(MockClient.verify_cost(&[]), BASE_VERIFY_COST);
+    assert_eq!(MockClient.verify_cost(&[0u8; 32]), BASE_VERIFY_COST + 32 * VERIFY_COST_PER_BYTE);
+}
+
+#[test]
+fn requests_should_compare_by_commitment() {
+    let post = Post {
+        from: vec![1u8; 32],
+        to: vec![2u8; 32],
+        ..mock_post(StateMachine::Kusama(2000), StateMachine::Kusama(2001), 0)
+    };
+    let request = Request::Post(post.clone());
+    assert!(request.commitment_eq::<Host>(&Request::Post(post.clone())));
+
+    // Metadata is committed to, so requests differing only in metadata are distinct
+    let with_meta = Request::Post(Post { meta: Some(vec![3u8; 32]), ..post.clone() });
+    assert!(!request.commitment_eq::<Host>(&with_meta));
+
+    // A receipt recorded in a snapshot is found by commitment
+    let snapshot = SnapshotHost::new(Host::default());
+    snapshot.store_request_receipt(&request).unwrap();
+    assert!(snapshot.request_receipt(&Request::Post(post)).is_some());
+    assert!(snapshot.request_receipt(&with_meta).is_none());
+}
+
+#[test]
+fn should_commit_to_get_read_height() {
+    let get = Get {
+        source: StateMachine::Kusama(2000),
+        dest: StateMachine::Kusama(2001),
+        nonce: 0,
+        from: vec![1u8; 32],
+        keys: vec![vec![2u8; 32]],
+        height: 1,
+        timeout_timestamp: 0,
+        gas_limit: 0,
+    };
+    let higher = Get { height: 2, ..get.clone() };
+
+    assert_ne!(
+        hash_request::<Host>(&Request::Get(get)),
+        hash_request::<Host>(&Request::Get(higher))
+    );
+}
+
+#[test]
+fn should_hash_requests_without_a_host() {
+    let get = Request::Get(Get {
+        source: StateMachine::Kusama(2000),
+        dest: StateMachine::Kusama(2001),
+        nonce: 0,
+        from: vec![1u8; 32],
+        keys: vec![vec![2u8; 32]],
+        height: 1,
+        timeout_timestamp: 0,
+        gas_limit: 0,
+    });
+
+    assert_eq!(hash_request_keccak(&get), hash_request::<Host>(&get));
+}
+
+#[test]
+fn should_verify_substrate_state_proofs() {
+    let mut db = MemoryDB::<Blake2Hasher>::default();
+    let mut root = H256::default();
+    {
+        let mut trie = TrieDBMutBuilder::<SubstrateLayout>::new(&mut db, &mut root).build();
+        // Values longer than 32 bytes are stored as separate nodes in the current layout
+        for i in 0u8..32 {
+            trie.insert(&[i; 32], &[i; 64]).unwrap();
+        }
+    }
+
+    // Record the nodes read for the keys, as a node does when serving a read proof
+    let present = vec![5u8; 32];
+    let absent = vec![200u8; 32];
+    let keys = vec![present.clone(), absent.clone()];
+    let recorder = Recorder::<Blake2Hasher>::default();
+    {
+        let mut trie_recorder = recorder.as_trie_recorder(root);
+        for key in &keys {
+            read_trie_value::<SubstrateLayout, _>(&db, &root, key, Some(&mut trie_recorder), None)
+                .unwrap();
+        }
+    }
+    let proof = recorder.drain_storage_proof().into_iter_nodes().collect::<Vec<_>>();
+
+    let values = verify_substrate_state_proof(root, &keys, &proof).unwrap();
+    assert_eq!(values, StateProofResult::from([(present, Some(vec![5u8; 64])), (absent, None)]));
+
+    // Proofs missing a node or built against another root are rejected
+    let res = verify_substrate_state_proof(root, &keys, &proof[1..]);
+    assert!(matches!(res, Err(Error::MembershipProofVerificationFailed(_))));
+    let res = verify_substrate_state_proof(H256::repeat_byte(1), &keys, &proof);
+    assert!(matches!(res, Err(Error::MembershipProofVerificationFailed(_))));
+}
+
+/// Builds a Merkle-Patricia trie of the given leaves with the reference implementation used by
+/// Ethereum clients, returning its root and the proof of the target key
+fn build_evm_trie(leaves: Vec<(B256, Vec<u8>)>, target: B256) -> (H256, Vec<Vec<u8>>) {
+    let mut leaves =
+        leaves.into_iter().map(|(key, value)| (Nibbles::unpack(key), value)).collect::<Vec<_>>();
+    leaves.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let retainer = ProofRetainer::new(vec![Nibbles::unpack(target)]);
+    let mut builder = HashBuilder::default().with_proof_retainer(retainer);
+    for (key, value) in &leaves {
+        builder.add_leaf(key.clone(), value);
+    }
+    let root = H256(builder.root().0);
+    let proof = builder
+        .take_proof_nodes()
+        .into_nodes_sorted()
+        .into_iter()
+        .map(|(_, node)| node.to_vec())
+        .collect();
+    (root, proof)
+}
+
+#[test]
+fn should_verify_evm_account_and_storage_proofs() {
+    let contract = H160::repeat_byte(0xaa);
+    let slot = |i: u64| H256::from_low_u64_be(i);
+    // Slots hold RLP encoded values, and empty slots are absent from the trie
+    let storage = (1..64u64)
+        .map(|i| (keccak256(slot(i)), alloy_rlp::encode(U256::from(i * 1000))))
+        .collect::<Vec<_>>();
+    let account_trie = |storage_root: H256| {
+        (0..64u8)
+            .map(|i| {
+                let account = TrieAccount {
+                    nonce: i as u64,
+                    balance: U256::from(i),
+                    storage_root: if i == 0xaa % 64 {
+                        B256::from(storage_root.0)
+                    } else {
+                        EMPTY_ROOT_HASH
+                    },
+                    code_hash: B256::repeat_byte(i),
+                };
+                let address = if i == 0xaa % 64 { contract } else { H160::repeat_byte(i) };
+                (keccak256(address), alloy_rlp::encode(account))
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let (storage_root, storage_proof) = build_evm_trie(storage.clone(), keccak256(slot(7)));
+    let (state_root, account_proof) =
+        build_evm_trie(account_trie(storage_root), keccak256(contract));
+    let proof = EvmProof { account_proof: account_proof.clone(), storage_proof };
+    let value = verify_account_and_storage(state_root, contract, slot(7), proof.clone()).unwrap();
+    assert_eq!(value, Some(7000u64.to_be_bytes()[6..].to_vec()));
+
+    // Empty slots and accounts are proven absent
+    let (_, absent_proof) = build_evm_trie(storage, keccak256(slot(100)));
+    let absent = EvmProof { account_proof: account_proof.clone(), storage_proof: absent_proof };
+    assert_eq!(verify_account_and_storage(state_root, contract, slot(100), absent).unwrap(), None);
+    let stranger = H160::repeat_byte(0xbb);
+    let (_, stranger_proof) = build_evm_trie(account_trie(storage_root), keccak256(stranger));
+    let absent = EvmProof { account_proof: stranger_proof, storage_proof: vec![] };
+    assert_eq!(verify_account_and_storage(state_root, stranger, slot(7), absent).unwrap(), None);
+    let (_, empty_storage) =
+        build_evm_trie(account_trie(storage_root), keccak256(H160::repeat_byte(1)));
+    let absent = EvmProof { account_proof: empty_storage, storage_proof: vec![] };
+    let res = verify_account_and_storage(state_root, H160::repeat_byte(1), slot(7), absent);
+    assert_eq!(res.unwrap(), None);
+
+    // Proofs missing a node or built against another root are rejected
+    let truncated = EvmProof { account_proof: account_proof[1..].to_vec(), ..proof.clone() };
+    let res = verify_account_and_storage(state_root, contract, slot(7), truncated);
+    assert!(matches!(res, Err(Error::MembershipProofVerificationFailed(_))));
+    let res = verify_account_and_storage(H256::repeat_byte(1), contract, slot(7), proof);
+    assert!(matches!(res, Err(Error::MembershipProofVerificationFailed(_))));
+
+    // Accounts whose storage root is not a 32 byte hash are rejected rather than panicking
+    let mut fields = alloy_rlp::encode(0u64);
+    fields.extend(alloy_rlp::encode(U256::ZERO));
+    fields.extend(alloy_rlp::encode(&[0xcc; 20][..]));
+    fields.extend(alloy_rlp::encode(B256::ZERO));
+    let mut account = Vec::new();
+    alloy_rlp::Header { list: true, payload_length: fields.len() }.encode(&mut account);
+    account.extend(fields);
+    let (state_root, account_proof) =
+        build_evm_trie(vec![(keccak256(contract), account)], keccak256(contract));
+    let malformed = EvmProof { account_proof, storage_proof: vec![] };
+    let res = verify_account_and_storage(state_root, contract, slot(7), malformed);
+    assert!(matches!(res, Err(Error::MembershipProofVerificationFailed(_))));
+}
+
+#[test]
+fn should_never_time_out_requests_with_the_largest_timeout() {
+    let post = |timeout_timestamp: u64| {
+        Request::Post(Post {
+            timeout_timestamp,
+            ..mock_post(StateMachine::Kusama(2000), StateMachine::Polkadot(1000), 0)
+        })
+    };
+
+    for request in [post(u64::MAX), post(0)] {
+        assert_eq!(request.timeout(), Duration::from_secs(u64::MAX));
+        assert!(!request.timed_out(Duration::from_secs(u64::MAX)));
+        assert!(!request.timed_out(Duration::MAX));
+    }
+    let request = post(u64::MAX - 1);
+    assert!(!request.timed_out(Duration::from_secs(u64::MAX - 2)));
+    assert!(request.timed_out(Duration::from_secs(u64::MAX)));
+}
+
+#[test]
+fn should_validate_consensus_client_registry() {
+    let host = Host::default();
+    host.validate_client_registry().unwrap();
+}
@@ -20,9 +20,9 @@ use crate::{
     host::{IsmpHost, StateMachine},
     messaging::{Proof, StateCommitmentHeight},
     prelude::Vec,
-    router::{Request, RequestResponse},
+    router::{Request, RequestResponseRef, Response},
 };
-use alloc::{boxed::Box, collections::BTreeMap};
+use alloc::{boxed::Box, collections::BTreeMap, string::ToString, vec};
 use codec::{Decode, Encode};
 use core::time::Duration;
 use primitive_types::H256;
@@ -33,10 +33,30 @@ pub type ConsensusStateId = [u8; 4];
 /// An identifier for Consensus client implementations
 pub type ConsensusClientId = [u8; 4];
 
+/// The conventional identifier of the beacon chain sync committee light client
+pub const SYNC_COMMITTEE_CLIENT_ID: ConsensusClientId = *b"SYNC";
+
+/// The conventional identifier of the parachain consensus client
+pub const PARACHAIN_CLIENT_ID: ConsensusClientId = *b"PARA";
+
+/// The conventional identifier of the GRANDPA consensus client
+pub const GRANDPA_CLIENT_ID: ConsensusClientId = *b"GRNP";
+
+/// The conventional identifier of the BEEFY consensus client
+pub const BEEFY_CLIENT_ID: ConsensusClientId = *b"BEEF";
+
+/// The conventional identifier of the Tendermint light client
+pub const TENDERMINT_CLIENT_ID: ConsensusClientId = *b"TNDR";
+
 /// The state commitment represents a commitment to the state machine's state (trie) at a given
 /// height. Optionally holds a commitment to the ISMP request/response trie if supported by the
 /// state machine.
-#[derive(Debug, Clone, Copy, Encode, Decode, scale_info::TypeInfo, PartialEq, Hash, Eq)]
+///
+/// Roots are fixed-width [`H256`] values, so a commitment with a root of the wrong length cannot
+/// be constructed or decoded and consensus clients need not validate root lengths themselves.
+#[derive(
+    Debug, Clone, Copy, Encode, Decode, scale_info::TypeInfo, PartialEq, Hash, Eq, Ord, PartialOrd,
+)]
 #[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
 pub struct StateCommitment {
     /// Timestamp in seconds
@@ -89,20 +109,180 @@ pub struct StateMachineHeight {
     pub height: u64,
 }
 
+impl StateMachineHeight {
+    /// Returns the next height for the same state machine, or `None` on overflow.
+    pub fn next(&self) -> Option<Self> {
+        Some(Self { id: self.id, height: self.height.checked_add(1)? })
+    }
+
+    /// Returns the previous height for the same state machine, or `None` at height zero.
+    pub fn prev(&self) -> Option<Self> {
+        Some(Self { id: self.id, height: self.height.checked_sub(1)? })
+    }
+}
+
+/// The fixed cost charged by the default [`ConsensusClient::verify_cost`] estimate
+pub const BASE_VERIFY_COST: u64 = 10_000;
+
+/// The cost per proof byte charged by the default [`ConsensusClient::verify_cost`] estimate
+pub const VERIFY_COST_PER_BYTE: u64 = 100;
+
 /// A map of state machine to verified state commitments
 pub type VerifiedCommitments = BTreeMap<StateMachine, Vec<StateCommitmentHeight>>;
 
+/// Describes the keys to be read from a state machine's global state trie at a given height.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateProof {
+    /// The raw keys to be read from the state trie
+    pub keys: Vec<Vec<u8>>,
+    /// The state machine height at which the keys should be read
+    pub height: StateMachineHeight,
+}
+
+/// A map of each key in a [`StateProof`] to its verified value, a `None` value means the key is
+/// absent from the state trie.
+pub type StateProofResult = BTreeMap<Vec<u8>, Option<Vec<u8>>>;
+
+/// Details of how a state proof was verified, for auditing proofs beyond the values they prove
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofMetadata {
+    /// The root the proof was verified against
+    pub root: H256,
+    /// The depth of the deepest proven key, zero if the client can't cheaply determine it
+    pub depth: usize,
+    /// The number of keys the proof verified
+    pub keys_verified: usize,
+}
+
+/// A client-agnostic summary of a consensus state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConsensusStateInfo {
+    /// The latest height the consensus state has been updated to
+    pub latest_height: u64,
+    /// Whether the consensus state is frozen
+    pub frozen: bool,
+    /// Time remaining until the current validator set's unbonding period elapses
+    pub next_unbonding: Duration,
+}
+
+/// The outcome of verifying a consensus proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerifyOutcome {
+    /// The proof is valid, holds the new consensus state and the state commitments it finalized
+    Verified(Vec<u8>, VerifiedCommitments),
+    /// The proof is valid and the consensus client rolled back to another fork, holds the new
+    /// consensus state and the state commitments finalized on that fork. For each state machine,
+    /// the last returned height becomes its latest height and the commitments above it that are
+    /// still within their challenge period are invalidated.
+    RolledBack(Vec<u8>, VerifiedCommitments),
+    /// The proof is evidence of byzantine behaviour, the consensus client will be frozen and the
+    /// evidence stored
+    Byzantine(Vec<u8>),
+}
+
+/// A verified consensus update held back until its own challenge period elapses, on hosts that
+/// apply consensus updates optimistically (see [`IsmpHost::optimistic_consensus_updates`])
+#[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
+pub struct PendingConsensusUpdate {
+    /// The new consensus state
+    pub consensus_state: Vec<u8>,
+    /// The state commitments finalized by the update, sorted by height
+    pub commitments: VerifiedCommitments,
+    /// The hash of the consensus proof, recorded once the update is promoted
+    pub proof_hash: H256,
+    /// The host time at which the update was verified
+    pub submitted_at: Duration,
+    /// Whether the consensus client rolled back to another fork, see [`VerifyOutcome::RolledBack`]
+    pub rolled_back: bool,
+}
+
 /// We define the consensus client as a module that handles logic for consensus proof verification,
 /// and State-Proof verification as well.
 pub trait ConsensusClient {
-    /// Verify the associated consensus proof, using the trusted consensus state.
+    /// Returns the identifier of this consensus client implementation. The default implementation
+    /// returns the zero identifier, clients listed in [`IsmpHost::consensus_clients`] must override
+    /// it to pass [`IsmpHost::validate_client_registry`].
+    fn consensus_client_id(&self) -> ConsensusClientId {
+        [0u8; 4]
+    }
+
+    /// Verify the associated consensus proof, using the trusted consensus state. If the proof
+    /// reveals byzantine behaviour this should return [`VerifyOutcome::Byzantine`] with the
+    /// evidence rather than an error, so that the consensus client is frozen.
     fn verify_consensus(
         &self,
         host: &dyn IsmpHost,
         consensus_state_id: ConsensusStateId,
         trusted_consensus_state: Vec<u8>,
         proof: Vec<u8>,
-    ) -> Result<(Vec<u8>, VerifiedCommitments), Error>;
+    ) -> Result<VerifyOutcome, Error>;
+
+    /// Verify the associated consensus proof without producing any side effects. Relayers may use
+    /// this to pre-validate a consensus proof off-chain before submitting it, the default
+    /// implementation runs the exact same logic as [`ConsensusClient::verify_consensus`] and
+    /// discards its output. Proofs revealing byzantine behaviour would freeze the client when
+    /// submitted, so they are reported as [`Error::ByzantineBehaviour`]. This only verifies the
+    /// proof itself, use [`crate::handlers::dry_run_update_client`] to also run the checks the
+    /// handler applies to consensus messages.
+    fn dry_run_verify(
+        &self,
+        host: &dyn IsmpHost,
+        consensus_state_id: ConsensusStateId,
+        trusted_consensus_state: Vec<u8>,
+        proof: Vec<u8>,
+    ) -> Result<(), Error> {
+        match self.verify_consensus(host, consensus_state_id, trusted_consensus_state, proof)? {
+            VerifyOutcome::Byzantine(_) => Err(Error::ByzantineBehaviour { consensus_state_id }),
+            VerifyOutcome::Verified(..) | VerifyOutcome::RolledBack(..) => Ok(()),
+        }
+    }
+
+    /// Returns the state proof format versions this client can verify, so that a single client can
+    /// accept both the old and new proof layouts across a chain upgrade. Relayers should produce
+    /// proofs in one of these formats. Defaults to only the original format, version zero.
+    fn supported_proof_versions(&self) -> Vec<u16> {
+        vec![0]
+    }
+
+    /// Returns the format version of a state proof. The version is carried inside the opaque
+    /// proof bytes, so that the encoding of [`Proof`] is the same for every format; clients
+    /// supporting more than one layout should read it from there. Defaults to version zero for
+    /// every proof.
+    fn proof_version(&self, _proof: &[u8]) -> Result<u16, Error> {
+        Ok(0)
+    }
+
+    /// Returns an estimate of the cost, in host-defined weight or gas units, of verifying the given
+    /// proof, so that hosts can pre-charge for it or reject it before verification. This is an
+    /// estimate and not a guarantee of the actual cost. The default grows linearly with the proof
+    /// length, clients whose cost depends on the proof structure should override it.
+    fn verify_cost(&self, proof: &[u8]) -> u64 {
+        BASE_VERIFY_COST.saturating_add(VERIFY_COST_PER_BYTE.saturating_mul(proof.len() as u64))
+    }
+
+    /// Returns true if proofs against the given state machine height can still be verified using
+    /// the provided consensus state. The default accepts heights from
+    /// [`ConsensusClient::earliest_verifiable_height`] onwards, clients which prune history in a
+    /// way a single lower bound can't describe should override it.
+    fn supports_height(&self, consensus_state: &[u8], height: u64) -> Result<bool, Error> {
+        Ok(height >= self.earliest_verifiable_height(consensus_state)?)
+    }
+
+    /// Returns the lowest state machine height that can still be verified against the provided
+    /// consensus state. Together with the latest height this gives relayers the window of heights
+    /// a proof may be built at. Should return [`Error::ConsensusStateDecodeFailed`] if the state
+    /// is malformed, the default implementation places no lower bound.
+    fn earliest_verifiable_height(&self, _consensus_state: &[u8]) -> Result<u64, Error> {
+        Ok(0)
+    }
+
+    /// Returns the consensus-level height of the provided consensus state, which must strictly
+    /// increase with every verified update. Clients that don't track such a height may return
+    /// `None`, which is the default, and the check is skipped. Should return
+    /// [`Error::ConsensusStateDecodeFailed`] if the state is malformed.
+    fn consensus_state_height(&self, _consensus_state: &[u8]) -> Result<Option<u64>, Error> {
+        Ok(None)
+    }
 
     /// Given two distinct consensus proofs, verify that they're both valid and represent
     /// conflicting views of the network. returns Ok(()) if they're both valid.
@@ -114,6 +294,15 @@ pub trait ConsensusClient {
         proof_2: Vec<u8>,
     ) -> Result<(), Error>;
 
+    /// Decode the consensus state and return a structured summary of it. Should return
+    /// [`Error::ConsensusStateDecodeFailed`] if the state is malformed. The default implementation
+    /// returns an error.
+    fn describe_state(&self, _consensus_state: &[u8]) -> Result<ConsensusStateInfo, Error> {
+        Err(Error::ImplementationSpecific(
+            "Describing consensus states is not supported".to_string(),
+        ))
+    }
+
     /// Return an implementation of a [`StateMachineClient`] for the given state machine.
     /// Return an error if the identifier is unknown.
     fn state_machine(&self, id: StateMachine) -> Result<Box<dyn StateMachineClient>, Error>;
@@ -126,20 +315,93 @@ pub trait StateMachineClient {
     fn verify_membership(
         &self,
         host: &dyn IsmpHost,
-        item: RequestResponse,
+        item: RequestResponseRef<'_>,
         root: StateCommitment,
         proof: &Proof,
     ) -> Result<(), Error>;
 
-    /// Transform the requests/responses into their equivalent key in the state trie.
+    /// Transform the requests/responses into their equivalent key in the state trie. For state
+    /// machines using the canonical storage layout these are the request receipt keys given by
+    /// [`crate::util::request_receipt_key`].
     fn state_trie_key(&self, request: Vec<Request>) -> Vec<Vec<u8>>;
 
-    /// Verify the state of proof of some arbitrary data. Should return the verified data
+    /// Verify the state proof of some arbitrary data. Should return the verified value for every
+    /// key in the [`StateProof`]
     fn verify_state_proof(
         &self,
         host: &dyn IsmpHost,
-        keys: Vec<Vec<u8>>,
+        state_proof: StateProof,
+        root: StateCommitment,
+        proof: &Proof,
+    ) -> Result<StateProofResult, Error>;
+
+    /// Verify the state proof of some arbitrary data like
+    /// [`StateMachineClient::verify_state_proof`] and also return metadata about the proof. The
+    /// default verifies the proof through [`StateMachineClient::verify_state_proof`] and
+    /// reports a depth of zero, clients that can determine the proof depth should override it.
+    fn verify_state_proof_with_metadata(
+        &self,
+        host: &dyn IsmpHost,
+        state_proof: StateProof,
         root: StateCommitment,
         proof: &Proof,
-    ) -> Result<BTreeMap<Vec<u8>, Option<Vec<u8>>>, Error>;
+    ) -> Result<(StateProofResult, ProofMetadata), Error> {
+        let values = self.verify_state_proof(host, state_proof, root, proof)?;
+        let metadata =
+            ProofMetadata { root: root.state_root, depth: 0, keys_verified: values.len() };
+        Ok((values, metadata))
+    }
+
+    /// Verify that the source of a batch of responses received the requests being responded to,
+    /// i.e. that a receipt for each request is present in its state trie. `receipt_proof` is a
+    /// state proof at the height the responses were proven at, it is separate from their
+    /// membership proof as the receipts may live in a different trie. As the receipts must be
+    /// present in the proven state, responses proven against a state predating the delivery of
+    /// their request are rejected. Returns an error if [`StateMachineClient::state_trie_key`]
+    /// doesn't return a receipt key for every request, since the binding can't be checked.
+    fn verify_response_binding(
+        &self,
+        host: &dyn IsmpHost,
+        responses: &[Response],
+        root: StateCommitment,
+        receipt_proof: &Proof,
+    ) -> Result<(), Error> {
+        if responses.is_empty() {
+            return Ok(())
+        }
+        let requests = responses.iter().map(Response::request).collect::<Vec<_>>();
+        let keys = self.state_trie_key(requests);
+        if keys.len() != responses.len() {
+            Err(Error::ImplementationSpecific(
+                "Expected a request receipt key for every response".to_string(),
+            ))?
+        }
+
+        let state_proof = StateProof { keys: keys.clone(), height: receipt_proof.height };
+        let values = self.verify_state_proof(host, state_proof, root, receipt_proof)?;
+        if let Some(key) = keys.into_iter().find(|key| !matches!(values.get(key), Some(Some(_)))) {
+            Err(Error::ResponseRequestBindingFailed { key })?
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "std")]
+    fn state_commitment_serializes_with_std() {
+        use super::StateCommitment;
+        use primitive_types::H256;
+
+        let commitment = StateCommitment {
+            timestamp: 1,
+            overlay_root: Some(H256::repeat_byte(1)),
+            state_root: H256::repeat_byte(2),
+        };
+        let json = serde_json::to_string(&commitment).unwrap();
+
+        assert_eq!(serde_json::from_str::<StateCommitment>(&json).unwrap(), commitment);
+    }
 }
@@ -16,11 +16,13 @@
 //! ISMP error definitions
 
 use crate::{
-    consensus::{ConsensusClientId, ConsensusStateId, StateMachineHeight},
-    host::StateMachine,
+    consensus::{ConsensusClientId, ConsensusStateId, StateMachineHeight, StateMachineId},
+    host::{FreezeReason, StateMachine},
+    module::ModuleId,
 };
 use alloc::{string::String, vec::Vec};
 use core::time::Duration;
+use primitive_types::H256;
 
 /// Errors that may be encountered by the ISMP module
 #[derive(Debug)]
@@ -60,6 +62,8 @@ pub enum Error {
     FrozenStateMachine {
         /// The given state machine height
         height: StateMachineHeight,
+        /// Why the state machine was frozen
+        reason: FreezeReason,
     },
     /// The given request was not found
     RequestCommitmentNotFound {
@@ -79,7 +83,7 @@ pub enum Error {
         /// The destination state machine
         dest: StateMachine,
     },
-    /// The given request has not yet timed-out
+    /// The given request has not yet timed-out, the timeout may be retried later
     RequestTimeoutNotElapsed {
         /// The request nonce
         nonce: u64,
@@ -92,7 +96,8 @@ pub enum Error {
         /// The current time on the state machine
         state_machine_time: Duration,
     },
-    /// The given request has failed non-membership state proof verification
+    /// The given request has failed non-membership state proof verification, i.e. it was received
+    /// on the destination and can not be timed out
     RequestTimeoutVerificationFailed {
         /// The request nonce
         nonce: u64,
@@ -115,6 +120,11 @@ pub enum Error {
         /// The consensus client identifier
         id: ConsensusClientId,
     },
+    /// The consensus proof reveals byzantine behaviour of the consensus state
+    ByzantineBehaviour {
+        /// The consensus state identifier
+        consensus_state_id: ConsensusStateId,
+    },
     /// The given consensus client has expired
     ExpiredConsensusClient {
         /// The consensus client identifier
@@ -160,4 +170,250 @@ pub enum Error {
         /// Consensus state Id
         consensus_state_id: ConsensusStateId,
     },
+
+    /// Consensus clients of this type are not allowed to be created on the host
+    ClientTypeNotAllowed {
+        /// The consensus client identifier
+        id: ConsensusClientId,
+    },
+
+    /// A verified consensus update did not advance the consensus state past the trusted state
+    ConsensusNotAdvanced {
+        /// The consensus state identifier
+        consensus_state_id: ConsensusStateId,
+        /// The height of the trusted consensus state
+        trusted_height: u64,
+        /// The height of the new consensus state
+        new_height: u64,
+    },
+
+    /// The consensus client is not the type conventionally used to track the state machine
+    UnexpectedClientType {
+        /// The state machine identifier
+        state_machine: StateMachine,
+        /// The conventional consensus client identifier, if the state machine has one
+        expected: Option<ConsensusClientId>,
+        /// The consensus client identifier that was provided
+        got: ConsensusClientId,
+    },
+
+    /// The message contains no requests, responses or proof
+    EmptyMessage,
+
+    /// The state machine is not supported by this host
+    UnsupportedStateMachine {
+        /// The unsupported state machine
+        state_machine: StateMachine,
+    },
+
+    /// The request format version is not supported by this host
+    UnsupportedRequestVersion {
+        /// The request nonce
+        nonce: u64,
+        /// The request version
+        version: u8,
+    },
+
+    /// The request's source and destination are the same state machine
+    SelfDispatch {
+        /// The state machine that is both the source and destination
+        state_machine: StateMachine,
+    },
+
+    /// The account is not authorized to perform this action
+    Unauthorized {
+        /// The account that attempted the action
+        account: Vec<u8>,
+    },
+
+    /// The message bytes could not be decoded within the configured limits
+    MessageDecodeFailed(String),
+
+    /// The consensus state could not be decoded
+    ConsensusStateDecodeFailed(String),
+
+    /// The host clock is behind the last recorded update time of the consensus client
+    NonMonotonicClock {
+        /// The consensus client identifier
+        consensus_state_id: ConsensusStateId,
+        /// The last time the consensus client was updated
+        update_time: Duration,
+        /// The current host time
+        current_time: Duration,
+    },
+
+    /// A state commitment has an earlier timestamp than a commitment at a lower height for the
+    /// same state machine
+    NonMonotonicCommitmentTimestamp {
+        /// The height of the offending commitment
+        height: StateMachineHeight,
+    },
+
+    /// The bytes are not the canonical encoding of a known state machine
+    UnknownStateMachine {
+        /// The unrecognized bytes
+        bytes: Vec<u8>,
+    },
+
+    /// Returned by a module's timeout callback to request that the timeout be retried later. The
+    /// request commitment is retained.
+    RetryTimeout,
+
+    /// The stored consensus state does not match the one the consensus proof was built against
+    TrustedStateMismatch {
+        /// The consensus state identifier
+        consensus_state_id: ConsensusStateId,
+        /// The expected hash of the trusted consensus state
+        expected: H256,
+        /// The hash of the stored consensus state
+        found: H256,
+    },
+
+    /// The GET request reads more keys than the host allows
+    TooManyKeys {
+        /// The request nonce, zero for requests rejected at dispatch before being assigned one
+        nonce: u64,
+        /// The number of keys in the request
+        keys: usize,
+        /// The maximum number of keys allowed by the host
+        max: usize,
+    },
+
+    /// No commitment height has been stored for the state machine
+    NoCommitmentHeight {
+        /// The state machine identifier
+        id: StateMachineId,
+    },
+
+    /// A response was received for, or dispatched to, a request that does not expect one
+    UnexpectedResponse {
+        /// The request nonce
+        nonce: u64,
+        /// The source of the response
+        source: StateMachine,
+        /// The destination of the response
+        dest: StateMachine,
+    },
+
+    /// A state proof did not yield a value for exactly the keys that were requested
+    ProofValueCountMismatch {
+        /// The number of distinct keys requested
+        expected: usize,
+        /// The number of values returned
+        got: usize,
+    },
+
+    /// The source of a response has no receipt for the request being responded to
+    ResponseRequestBindingFailed {
+        /// The state trie key of the missing request receipt
+        key: Vec<u8>,
+    },
+
+    /// The host requires a proof of the request receipts for responses but none was provided
+    MissingReceiptProof,
+
+    /// A module is already registered under the identifier
+    DuplicateModule {
+        /// The module identifier
+        id: ModuleId,
+    },
+
+    /// The module is not registered on the host
+    UnknownModule {
+        /// The module identifier
+        id: ModuleId,
+    },
+
+    /// The consensus client can no longer verify proofs at the given height
+    HeightPruned {
+        /// The pruned state machine height
+        height: StateMachineHeight,
+    },
+
+    /// The consensus proof was already applied to the consensus state
+    DuplicateConsensusProof {
+        /// The consensus state identifier
+        consensus_state_id: ConsensusStateId,
+    },
+
+    /// The request is not addressed to the host state machine
+    WrongDestination {
+        /// The host state machine
+        expected: StateMachine,
+        /// The destination of the request
+        got: StateMachine,
+    },
+
+    /// The consensus client id is registered more than once or resolves to an implementation with
+    /// a different id
+    AmbiguousConsensusClient {
+        /// The consensus client identifier
+        id: ConsensusClientId,
+    },
+
+    /// The metadata attached to a request exceeds the maximum size
+    MetadataTooLarge {
+        /// The size of the metadata
+        len: usize,
+        /// The maximum size of the metadata
+        max: usize,
+    },
+
+    /// A mandatory module identifier of an outgoing request is empty
+    EmptyModuleId {
+        /// The name of the empty field
+        field: String,
+    },
+
+    /// The message was built against an incompatible major protocol version
+    IncompatibleProtocolVersion {
+        /// The version declared by the message
+        version: u16,
+        /// The version implemented by the host
+        supported: u16,
+    },
+
+    /// An item in a batch has a different source or destination to the rest of the batch
+    HeterogeneousBatch {
+        /// The nonce of the offending item
+        nonce: u64,
+        /// The source of the offending item
+        source: StateMachine,
+        /// The destination of the offending item
+        dest: StateMachine,
+    },
+
+    /// A state machine commitment belongs to a different consensus state
+    ConsensusStateIdMismatch {
+        /// The consensus state the commitment was expected to belong to
+        expected: ConsensusStateId,
+        /// The consensus state the commitment belongs to
+        got: ConsensusStateId,
+    },
+
+    /// A state machine commitment is at height zero
+    ZeroCommitmentHeight {
+        /// The state machine the commitment belongs to
+        id: StateMachineId,
+    },
+
+    /// A state machine commitment has a zero state root
+    ZeroStateRoot {
+        /// The height of the commitment
+        height: StateMachineHeight,
+    },
+
+    /// More than one state commitment was provided for the same state machine height
+    DuplicateStateCommitment {
+        /// The repeated height
+        height: StateMachineHeight,
+    },
+
+    /// The consensus client can't verify proofs in the given format
+    UnsupportedProofVersion {
+        /// The format version of the proof
+        version: u16,
+        /// The format versions supported by the consensus client
+        supported: Vec<u16>,
+    },
 }
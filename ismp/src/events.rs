@@ -4,8 +4,9 @@ use crate::{
     consensus::{ConsensusStateId, StateMachineHeight, StateMachineId},
     router::{Get, Post, PostResponse},
 };
-use alloc::collections::BTreeSet;
+use alloc::{collections::BTreeSet, string::String, vec::Vec};
 use codec::{Decode, Encode};
+use primitive_types::H256;
 use scale_info::TypeInfo;
 
 /// Emitted when a state machine is successfully updated to a new height after the challenge period
@@ -29,6 +30,53 @@ pub struct ChallengePeriodStarted {
     pub state_machines: BTreeSet<(StateMachineHeight, StateMachineHeight)>,
 }
 
+/// Emitted when a frozen state machine is unfrozen by governance
+#[derive(Clone, Debug, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateMachineUnfrozen {
+    /// The state machine height that was unfrozen
+    pub height: StateMachineHeight,
+    /// The account that authorized the unfreeze
+    pub authority: Vec<u8>,
+    /// Host timestamp in seconds at which the state machine was unfrozen
+    pub timestamp: u64,
+}
+
+/// A stage in the lifecycle of a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub enum TraceStage {
+    /// The request was dispatched on its source
+    Dispatched,
+    /// The request was delivered to its destination module
+    Delivered,
+    /// The request was timed out on its source
+    TimedOut,
+}
+
+/// A trace of a request reaching a stage in its lifecycle, correlated across hosts by the request
+/// commitment
+#[derive(Clone, Debug, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub struct TraceEvent {
+    /// The request commitment
+    pub commitment: H256,
+    /// The lifecycle stage that was reached
+    pub stage: TraceStage,
+    /// Host timestamp in seconds at which the stage was reached
+    pub timestamp: u64,
+}
+
+/// The outcome of delivering an incoming request to its destination module
+#[derive(Clone, Debug, PartialEq, Eq, TypeInfo, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeliveryOutcome {
+    /// The module accepted the request
+    Delivered,
+    /// The module rejected the request, holds the reason
+    ModuleRejected(String),
+}
+
 /// This represents events that should be emitted by ismp-rs wrappers
 #[derive(Clone, Debug, TypeInfo, Encode, Decode)]
 #[cfg_attr(feature = "std", derive(serde::Serialize, serde::Deserialize))]
@@ -44,4 +92,6 @@ pub enum Event {
     PostResponse(PostResponse),
     /// An event that is emitted when a get request is dispatched
     GetRequest(Get),
+    /// Emitted when a frozen state machine is unfrozen by governance
+    StateMachineUnfrozen(StateMachineUnfrozen),
 }
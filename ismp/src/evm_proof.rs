@@ -0,0 +1,185 @@
+// Copyright (C) Polytope Labs Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of the Merkle-Patricia proofs of an account and one of its storage slots returned
+//! by `eth_getProof` (EIP-1186), for EVM consensus clients to delegate
+//! [`StateMachineClient::verify_state_proof`] to.
+//!
+//! [`StateMachineClient::verify_state_proof`]: crate::consensus::StateMachineClient::verify_state_proof
+
+use crate::{
+    error::Error,
+    util::{BuiltinKeccak, Keccak256},
+};
+use alloc::{collections::BTreeMap, format, string::ToString, vec::Vec};
+use codec::{Decode, Encode};
+use primitive_types::{H160, H256};
+use rlp::Rlp;
+
+/// The root of an empty Merkle-Patricia trie, i.e. the keccak256 hash of the RLP empty string
+pub const EMPTY_TRIE_ROOT: H256 = H256([
+    0x56, 0xe8, 0x1f, 0x17, 0x1b, 0xcc, 0x55, 0xa6, 0xff, 0x83, 0x45, 0xe6, 0x92, 0xc0, 0xf8, 0x6e,
+    0x5b, 0x48, 0xe0, 0x1b, 0x99, 0x6c, 0xad, 0xc0, 0x01, 0x62, 0x2f, 0xb5, 0xe3, 0x63, 0xb4, 0x21,
+]);
+
+/// The proof of an account and one of its storage slots, as returned by `eth_getProof`
+#[derive(Debug, Clone, Encode, Decode, scale_info::TypeInfo, PartialEq, Eq)]
+pub struct EvmProof {
+    /// The RLP encoded trie nodes on the path from the state root to the account
+    pub account_proof: Vec<Vec<u8>>,
+    /// The RLP encoded trie nodes on the path from the account's storage root to the slot
+    pub storage_proof: Vec<Vec<u8>>,
+}
+
+/// Verify the account proof against the state root, then the storage proof against the storage
+/// root of the account. Returns the value of the storage slot with its leading zeroes stripped, or
+/// `None` if either the account or the slot is absent. Returns
+/// [`Error::MembershipProofVerificationFailed`] if a proof is incomplete or malformed.
+pub fn verify_account_and_storage(
+    state_root: H256,
+    address: H160,
+    storage_key: H256,
+    proof: EvmProof,
+) -> Result<Option<Vec<u8>>, Error> {
+    let account_path = BuiltinKeccak::keccak256(address.as_bytes());
+    let Some(account) = verify_trie_proof(state_root, account_path, &proof.account_proof)? else {
+        return Ok(None)
+    };
+    // Accounts are encoded as [nonce, balance, storage_root, code_hash]
+    let storage_root = match Rlp::new(&account).at(2).and_then(|root| root.data()) {
+        Ok(root) if root.len() == 32 => H256::from_slice(root),
+        Ok(_) => Err(Error::MembershipProofVerificationFailed("Invalid storage root".to_string()))?,
+        Err(e) => Err(invalid_node(e))?,
+    };
+
+    let storage_path = BuiltinKeccak::keccak256(storage_key.as_bytes());
+    let Some(value) = verify_trie_proof(storage_root, storage_path, &proof.storage_proof)? else {
+        return Ok(None)
+    };
+    // Storage values are RLP encoded integers
+    Rlp::new(&value).data().map(|data| Some(data.to_vec())).map_err(invalid_node)
+}
+
+/// A reference from a trie node to a child node
+enum NodeRef<'a> {
+    /// The child is stored under its hash
+    Hash(H256),
+    /// The child is shorter than a hash and embedded in its parent
+    Inline(&'a [u8]),
+}
+
+/// Verify a proof of the value stored under `path` in the trie with the given root. Returns the
+/// value, or `None` if the proof shows the path is absent.
+pub fn verify_trie_proof(
+    root: H256,
+    path: H256,
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, Error> {
+    if root == EMPTY_TRIE_ROOT {
+        return Ok(None)
+    }
+
+    let nodes = proof
+        .iter()
+        .map(|node| (BuiltinKeccak::keccak256(node), &node[..]))
+        .collect::<BTreeMap<_, _>>();
+    let nibbles =
+        path.as_bytes().iter().flat_map(|byte| [byte >> 4, byte & 0x0f]).collect::<Vec<_>>();
+    let mut next = NodeRef::Hash(root);
+    let mut offset = 0;
+
+    loop {
+        let node = match next {
+            NodeRef::Hash(hash) => *nodes.get(&hash).ok_or_else(|| {
+                Error::MembershipProofVerificationFailed(format!("Missing trie node {hash:?}"))
+            })?,
+            NodeRef::Inline(node) => node,
+        };
+        let node = Rlp::new(node);
+        match node.item_count().map_err(invalid_node)? {
+            // Branch node
+            17 => {
+                let Some(&nibble) = nibbles.get(offset) else {
+                    let value = node.at(16).and_then(|value| value.data()).map_err(invalid_node)?;
+                    return Ok((!value.is_empty()).then(|| value.to_vec()))
+                };
+                let child = node.at(nibble as usize).map_err(invalid_node)?;
+                if child.is_empty() {
+                    return Ok(None)
+                }
+                next = node_ref(&child)?;
+                offset += 1;
+            }
+            // Leaf or extension node
+            2 => {
+                let encoded_path = node.at(0).and_then(|path| path.data()).map_err(invalid_node)?;
+                let (partial, is_leaf) = decode_hex_prefix(encoded_path)?;
+                let remaining = &nibbles[offset..];
+                if is_leaf {
+                    if remaining != &partial[..] {
+                        return Ok(None)
+                    }
+                    let value = node.at(1).and_then(|value| value.data()).map_err(invalid_node)?;
+                    return Ok(Some(value.to_vec()))
+                }
+                if !remaining.starts_with(&partial) {
+                    return Ok(None)
+                }
+                next = node_ref(&node.at(1).map_err(invalid_node)?)?;
+                offset += partial.len();
+            }
+            count => Err(Error::MembershipProofVerificationFailed(format!(
+                "Trie node has {count} items"
+            )))?,
+        }
+    }
+}
+
+/// Returns the reference to a child node held by a branch or extension node
+fn node_ref<'a>(child: &Rlp<'a>) -> Result<NodeRef<'a>, Error> {
+    if child.is_list() {
+        return Ok(NodeRef::Inline(child.as_raw()))
+    }
+    match child.data().map_err(invalid_node)? {
+        hash if hash.len() == 32 => Ok(NodeRef::Hash(H256::from_slice(hash))),
+        _ => Err(Error::MembershipProofVerificationFailed("Invalid child reference".to_string())),
+    }
+}
+
+/// Decode the hex prefix encoded path of a leaf or extension node into its nibbles, and whether
+/// the node is a leaf
+fn decode_hex_prefix(encoded: &[u8]) -> Result<(Vec<u8>, bool), Error> {
+    let (&first, rest) = encoded.split_first().ok_or_else(|| {
+        Error::MembershipProofVerificationFailed("Empty trie node path".to_string())
+    })?;
+    let flag = first >> 4;
+    if flag > 3 {
+        Err(Error::MembershipProofVerificationFailed(format!(
+            "Invalid trie node path flag {flag}"
+        )))?
+    }
+
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    // Odd length paths carry their first nibble alongside the flag
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    }
+    nibbles.extend(rest.iter().flat_map(|byte| [byte >> 4, byte & 0x0f]));
+    Ok((nibbles, flag & 2 == 2))
+}
+
+fn invalid_node(e: rlp::DecoderError) -> Error {
+    Error::MembershipProofVerificationFailed(format!("Invalid trie node: {e:?}"))
+}
@@ -15,15 +15,32 @@
 
 //! ISMP handler definitions
 use crate::{
-    consensus::{ConsensusClientId, StateMachineClient, StateMachineHeight},
+    consensus::{
+        ConsensusClient, ConsensusClientId, StateCommitment, StateMachineClient,
+        StateMachineHeight, StateMachineId, StateProofResult,
+    },
     error::Error,
-    host::IsmpHost,
-    messaging::Message,
+    host::{IsmpHost, StateMachine, UnknownPolicy},
+    messaging::{Message, Proof},
+    router::{Request, RequestResponseRef},
+    util::now_from_host,
+    version::{is_compatible, ISMP_PROTOCOL_VERSION},
 };
 
 use crate::{consensus::ConsensusStateId, module::DispatchResult};
-use alloc::{boxed::Box, collections::BTreeSet, vec::Vec};
-pub use consensus::create_client;
+use alloc::{
+    boxed::Box,
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+    vec::Vec,
+};
+use codec::{Decode, Encode};
+pub use consensus::{
+    acknowledge_finalized_commitments, create_client, dry_run_update_client,
+    promote_pending_update, record_consensus_ack, set_unbonding_period, unfreeze_state_machine,
+};
+use core::cell::RefCell;
+pub(crate) use request::validate_request_message;
 
 mod consensus;
 mod request;
@@ -31,7 +48,8 @@ mod response;
 mod timeout;
 
 /// The result of successfully processing a [`ConsensusMessage`]
-#[derive(Debug)]
+#[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
 pub struct ConsensusUpdateResult {
     /// Consensus client Id
     pub consensus_client_id: ConsensusClientId,
@@ -39,9 +57,33 @@ pub struct ConsensusUpdateResult {
     pub consensus_state_id: ConsensusStateId,
     /// Tuple of previous latest height and new latest height for a state machine
     pub state_updates: BTreeSet<(StateMachineHeight, StateMachineHeight)>,
+    /// The state commitments that were stored by this update
+    commitments: Vec<(StateMachineHeight, StateCommitment)>,
+}
+
+impl ConsensusUpdateResult {
+    /// Returns the state commitments that were stored by this update, in the order they were
+    /// stored.
+    pub fn new_commitments(&self) -> &[(StateMachineHeight, StateCommitment)] {
+        &self.commitments
+    }
+
+    /// Returns true if the update advanced the consensus state without committing any new state
+    /// machine heights, e.g. an authority set rotation. Such updates are valid.
+    pub fn is_housekeeping(&self) -> bool {
+        self.state_updates.is_empty()
+    }
+
+    /// Returns the state machines whose latest height was updated, modules caching reads against
+    /// these state machines may need to invalidate them.
+    pub fn advanced_machines(&self) -> BTreeSet<StateMachineId> {
+        self.state_updates.iter().map(|(_, latest)| latest.id).collect()
+    }
 }
 
 /// The result of successfully processing a [`CreateConsensusClient`] message
+#[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
 pub struct ConsensusClientCreatedResult {
     /// Consensus client Id
     pub consensus_client_id: ConsensusClientId,
@@ -49,35 +91,119 @@ pub struct ConsensusClientCreatedResult {
     pub consensus_state_id: ConsensusStateId,
 }
 
-/// Result returned when ismp messages are handled successfully
-#[derive(Debug)]
+/// Result returned when ismp messages are handled successfully. Results can be encoded so that
+/// messages handled in a separate worker can be reported back to a coordinator.
+#[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
 pub enum MessageResult {
     /// The [`ConsensusMessage`] result
     ConsensusMessage(ConsensusUpdateResult),
+    /// The [`ConsensusMessage`] was verified and stored as pending, on hosts that apply consensus
+    /// updates optimistically
+    ConsensusUpdatePending {
+        /// Consensus state Id
+        consensus_state_id: ConsensusStateId,
+        /// The previously pending update, promoted to make way for this one
+        promoted: Option<ConsensusUpdateResult>,
+    },
     /// Result of freezing a consensus state.
     FrozenClient(ConsensusStateId),
-    /// The [`DispatchResult`] for requests
+    /// The [`DispatchResult`] for requests, the source and destination are those of the request.
     Request(Vec<DispatchResult>),
-    /// The [`DispatchResult`] for responses
+    /// The [`DispatchResult`] for responses. For POST responses the source and destination are
+    /// those of the response, i.e. the source is the chain the request was sent to. GET responses
+    /// are read from the destination's state rather than sent by it, so their results carry the
+    /// source and destination of the GET request.
     Response(Vec<DispatchResult>),
-    /// The [`DispatchResult`] for timeouts
+    /// The [`DispatchResult`] for timeouts. Although a timeout is delivered back to the origin of
+    /// the request, the source and destination are not swapped: they are those of the timed-out
+    /// request, so the source is this host.
     Timeout(Vec<DispatchResult>),
 }
 
+/// Consensus clients resolved while handling messages, so that each client is only looked up and
+/// boxed once no matter how many messages it secures.
+#[derive(Default)]
+pub(crate) struct ClientCache {
+    clients: RefCell<BTreeMap<ConsensusClientId, Rc<dyn ConsensusClient>>>,
+}
+
+impl ClientCache {
+    /// Returns the consensus client with the given id, looking it up on the host the first time
+    pub(crate) fn get<H>(
+        &self,
+        host: &H,
+        id: ConsensusClientId,
+    ) -> Result<Rc<dyn ConsensusClient>, Error>
+    where
+        H: IsmpHost,
+    {
+        if let Some(client) = self.clients.borrow().get(&id) {
+            return Ok(client.clone())
+        }
+        let client = Rc::<dyn ConsensusClient>::from(host.consensus_client(id)?);
+        self.clients.borrow_mut().insert(id, client.clone());
+        Ok(client)
+    }
+}
+
 /// This function serves as an entry point to handle the message types provided by the ISMP protocol
 pub fn handle_incoming_message<H>(host: &H, message: Message) -> Result<MessageResult, Error>
 where
     H: IsmpHost,
 {
+    handle_message(host, message, &ClientCache::default())
+}
+
+/// Handle a batch of messages in order, returning the result of each. A failed message does not
+/// stop the rest of the batch. Consensus clients are looked up once per batch rather than once
+/// per message, so a batch of `n` messages secured by `k` distinct clients boxes `k` clients
+/// instead of `n`.
+pub fn handle_incoming_messages<H>(
+    host: &H,
+    messages: Vec<Message>,
+) -> Vec<Result<MessageResult, Error>>
+where
+    H: IsmpHost,
+{
+    let clients = ClientCache::default();
+    messages.into_iter().map(|message| handle_message(host, message, &clients)).collect()
+}
+
+fn handle_message<H>(
+    host: &H,
+    message: Message,
+    clients: &ClientCache,
+) -> Result<MessageResult, Error>
+where
+    H: IsmpHost,
+{
+    if let Some(version) = message.protocol_version() {
+        if !is_compatible(version) {
+            Err(Error::IncompatibleProtocolVersion { version, supported: ISMP_PROTOCOL_VERSION })?
+        }
+    }
+
     match message {
-        Message::Consensus(consensus_message) => consensus::update_client(host, consensus_message),
-        Message::FraudProof(fraud_proof) => consensus::freeze_client(host, fraud_proof),
-        Message::Request(req) => request::handle(host, req),
-        Message::Response(resp) => response::handle(host, resp),
-        Message::Timeout(timeout) => timeout::handle(host, timeout),
+        Message::Consensus(consensus_message) => {
+            consensus::update_client(host, consensus_message, clients)
+        }
+        Message::FraudProof(fraud_proof) => consensus::freeze_client(host, fraud_proof, clients),
+        Message::Request(req) => request::handle(host, req, clients),
+        Message::Response(resp) => response::handle(host, resp, clients),
+        Message::Timeout(timeout) => timeout::handle(host, timeout, clients),
     }
 }
 
+/// Decode a message from untrusted bytes with [`Message::from_scale_bytes`] and handle it.
+pub fn handle_incoming_bytes<H>(host: &H, bytes: &[u8]) -> Result<MessageResult, Error>
+where
+    H: IsmpHost,
+{
+    let message = Message::from_scale_bytes(bytes)?;
+    handle_incoming_message(host, message)
+}
+
 /// This function checks to see that the delay period configured on the host chain
 /// for the state machine has elasped.
 fn verify_delay_passed<H>(host: &H, proof_height: &StateMachineHeight) -> Result<bool, Error>
@@ -90,42 +216,150 @@ where
             consensus_state_id: proof_height.id.consensus_state_id,
         },
     )?;
-    let current_timestamp = host.timestamp();
-    Ok(current_timestamp - update_time > delay_period)
+    let current_time = now_from_host(host);
+    let elapsed = current_time.checked_sub(update_time).ok_or(Error::NonMonotonicClock {
+        consensus_state_id: proof_height.id.consensus_state_id,
+        update_time,
+        current_time,
+    })?;
+    Ok(elapsed > delay_period)
 }
 
 /// This function does the preliminary checks for a request or response message
 /// - It ensures the consensus client is not frozen
 /// - It ensures the state machine is not frozen
+/// - It ensures the consensus client can still verify proofs at the height
 /// - Checks that the delay period configured for the state machine has elaspsed.
-fn validate_state_machine<H>(
+pub(crate) fn validate_state_machine<H>(
     host: &H,
-    proof_height: StateMachineHeight,
+    proof: &Proof,
+    clients: &ClientCache,
 ) -> Result<Box<dyn StateMachineClient>, Error>
 where
     H: IsmpHost,
 {
+    let proof_height = proof.height;
     // Ensure consensus client is not frozen
     let consensus_client_id = host.consensus_client_id(proof_height.id.consensus_state_id).ok_or(
         Error::ConsensusStateIdNotRecognized {
             consensus_state_id: proof_height.id.consensus_state_id,
         },
     )?;
-    let consensus_client = host.consensus_client(consensus_client_id)?;
+    let consensus_client = clients.get(host, consensus_client_id)?;
+    // Ensure the consensus client understands the proof format
+    let version = consensus_client.proof_version(&proof.proof)?;
+    let supported = consensus_client.supported_proof_versions();
+    if !supported.contains(&version) {
+        Err(Error::UnsupportedProofVersion { version, supported })?
+    }
     // Ensure client is not frozen
     host.is_consensus_client_frozen(proof_height.id.consensus_state_id)?;
 
     // Ensure state machine is not frozen
     host.is_state_machine_frozen(proof_height)?;
 
+    // Ensure the consensus client can still prove this height
+    let consensus_state = host.consensus_state(proof_height.id.consensus_state_id)?;
+    if !consensus_client.supports_height(&consensus_state, proof_height.height)? {
+        Err(Error::HeightPruned { height: proof_height })?
+    }
+
     // Ensure delay period has elapsed
     if !verify_delay_passed(host, &proof_height)? {
         return Err(Error::ChallengePeriodNotElapsed {
             consensus_state_id: proof_height.id.consensus_state_id,
-            current_time: host.timestamp(),
+            current_time: now_from_host(host),
             update_time: host.state_machine_update_time(proof_height)?,
         })
     }
 
     consensus_client.state_machine(proof_height.id.state_id)
 }
+
+/// Ensures no GET request reads more keys than the host allows
+pub(crate) fn check_get_keys<H>(host: &H, requests: &[Request]) -> Result<(), Error>
+where
+    H: IsmpHost,
+{
+    let Some(max) = host.max_get_keys() else { return Ok(()) };
+    for request in requests {
+        if let Request::Get(get) = request {
+            if get.keys.len() > max {
+                Err(Error::TooManyKeys { nonce: get.nonce, keys: get.keys.len(), max })?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Ensures a state machine client returned a value for exactly the keys it was asked to verify,
+/// so that no value can be attributed to the wrong key.
+pub(crate) fn check_proof_values(keys: &[Vec<u8>], values: &StateProofResult) -> Result<(), Error> {
+    let expected = keys.iter().collect::<BTreeSet<_>>();
+    if expected.len() != values.len() || !values.keys().all(|key| expected.contains(key)) {
+        Err(Error::ProofValueCountMismatch { expected: expected.len(), got: values.len() })?
+    }
+
+    Ok(())
+}
+
+/// Applies the host's [`UnknownPolicy`] to the source of incoming requests. Returns false if the
+/// requests should be dropped. Requests relayed through an allowed proxy are always accepted,
+/// since their source need not be tracked by the host.
+pub(crate) fn check_known_source<H>(
+    host: &H,
+    source: StateMachine,
+    proof_state_machine: StateMachine,
+) -> Result<bool, Error>
+where
+    H: IsmpHost,
+{
+    if host.is_supported_state_machine(&source) || host.is_allowed_proxy(&proof_state_machine) {
+        return Ok(true)
+    }
+
+    match host.on_unknown_state_machine() {
+        UnknownPolicy::Reject => Err(Error::UnsupportedStateMachine { state_machine: source }),
+        UnknownPolicy::Ignore => Ok(false),
+    }
+}
+
+/// Ensures every item in a batch shares the same source and destination, given as
+/// `(nonce, source, dest)`. A single proof commits to the state of a single state machine, so
+/// mixing items from different chains in one batch is rejected outright.
+pub(crate) fn check_homogeneous_batch(
+    items: impl IntoIterator<Item = (u64, StateMachine, StateMachine)>,
+) -> Result<(), Error> {
+    let mut items = items.into_iter();
+    let Some((_, source, dest)) = items.next() else { return Ok(()) };
+    if let Some((nonce, source, dest)) =
+        items.find(|(_, other_source, other_dest)| (*other_source, *other_dest) != (source, dest))
+    {
+        Err(Error::HeterogeneousBatch { nonce, source, dest })?
+    }
+
+    Ok(())
+}
+
+/// Verifies the membership proof of a batch, skipping verification if the host has recently
+/// verified the same proof of the same batch against the same state commitment.
+fn verify_membership_cached<H>(
+    host: &H,
+    state_machine: &dyn StateMachineClient,
+    item: RequestResponseRef<'_>,
+    state: StateCommitment,
+    proof: &Proof,
+) -> Result<(), Error>
+where
+    H: IsmpHost,
+{
+    let proof_hash = H::keccak256(&(proof, item, state).encode());
+    if host.is_proof_verified(proof_hash) {
+        return Ok(())
+    }
+
+    state_machine.verify_membership(host, item, state, proof)?;
+    host.cache_verified_proof(proof_hash);
+    Ok(())
+}
@@ -16,57 +16,355 @@
 //! The ISMP consensus handler
 
 use crate::{
-    consensus::{StateMachineHeight, StateMachineId},
+    consensus::{
+        ConsensusClient, ConsensusClientId, ConsensusStateId, PendingConsensusUpdate,
+        StateMachineHeight, StateMachineId, VerifyOutcome,
+    },
     error::Error,
-    handlers::{ConsensusClientCreatedResult, ConsensusUpdateResult, MessageResult},
+    events::StateMachineUnfrozen,
+    handlers::{ClientCache, ConsensusClientCreatedResult, ConsensusUpdateResult, MessageResult},
     host::IsmpHost,
-    messaging::{ConsensusMessage, CreateConsensusState, FraudProofMessage},
+    messaging::{
+        ConsensusAckMessage, ConsensusMessage, CreateConsensusState, FraudProofMessage,
+        SetUnbondingPeriodMessage, UnfreezeStateMachineMessage,
+    },
+    util::now_from_host,
+    version::{is_compatible, ISMP_PROTOCOL_VERSION},
 };
-use alloc::{collections::BTreeSet, string::ToString};
+use alloc::{collections::BTreeSet, string::ToString, vec::Vec};
+use codec::Encode;
+use core::time::Duration;
+use primitive_types::H256;
 
 /// This function handles verification of consensus messages for consensus clients
-pub fn update_client<H>(host: &H, msg: ConsensusMessage) -> Result<MessageResult, Error>
+pub(crate) fn update_client<H>(
+    host: &H,
+    msg: ConsensusMessage,
+    clients: &ClientCache,
+) -> Result<MessageResult, Error>
 where
     H: IsmpHost,
 {
-    let consensus_client_id = host.consensus_client_id(msg.consensus_state_id).ok_or(
-        Error::ConsensusStateIdNotRecognized { consensus_state_id: msg.consensus_state_id },
-    )?;
+    let consensus_state_id = msg.consensus_state_id;
+    let consensus_client_id = host
+        .consensus_client_id(consensus_state_id)
+        .ok_or(Error::ConsensusStateIdNotRecognized { consensus_state_id })?;
+    let consensus_client = clients.get(host, consensus_client_id)?;
+    let now = now_from_host(host);
+    let update_time = check_update_time(host, consensus_state_id, now)?;
+
+    let optimistic = host.optimistic_consensus_updates();
+    let promoted = if optimistic {
+        // The challenge period applies to the pending update instead, which has to be promoted
+        // before it can be replaced.
+        let promoted = promote_pending_update(host, consensus_state_id)?;
+        if let Some(pending) = host.pending_consensus_update(consensus_state_id) {
+            Err(Error::ChallengePeriodNotElapsed {
+                consensus_state_id,
+                current_time: now,
+                update_time: pending.submitted_at,
+            })?
+        }
+        promoted
+    } else {
+        check_challenge_period(host, consensus_state_id, now, update_time)?;
+        None
+    };
+
+    host.is_expired(consensus_state_id)?;
+
+    let trusted_state = host.consensus_state(consensus_state_id)?;
+    let last_proof = host.last_consensus_proof(consensus_state_id);
+    let update = match verify_update(host, &*consensus_client, msg, trusted_state, last_proof, now)?
+    {
+        VerifiedUpdate::Update(update) => update,
+        // Byzantine behaviour was detected, freeze the client and keep the evidence
+        VerifiedUpdate::Byzantine(evidence) => {
+            host.freeze_consensus_client(consensus_state_id)?;
+            host.store_byzantine_evidence(consensus_state_id, evidence)?;
+            return Ok(MessageResult::FrozenClient(consensus_state_id))
+        }
+    };
+    if optimistic {
+        host.store_pending_consensus_update(consensus_state_id, Some(update))?;
+        return Ok(MessageResult::ConsensusUpdatePending { consensus_state_id, promoted })
+    }
+
+    let result = apply_update(host, consensus_client_id, consensus_state_id, update)?;
+
+    Ok(MessageResult::ConsensusMessage(result))
+}
+
+/// Run every check the consensus message handler applies to a consensus message without storing
+/// anything, so that relayers can find out whether it would be accepted before submitting it. On
+/// hosts that apply updates optimistically, a pending update whose challenge period has elapsed
+/// would be promoted first, so the message is checked against the pending consensus state.
+/// Returns [`Error::ByzantineBehaviour`] for proofs that would freeze the client.
+pub fn dry_run_update_client<H>(host: &H, msg: ConsensusMessage) -> Result<(), Error>
+where
+    H: IsmpHost,
+{
+    if let Some(version) = msg.protocol_version.filter(|version| !is_compatible(*version)) {
+        Err(Error::IncompatibleProtocolVersion { version, supported: ISMP_PROTOCOL_VERSION })?
+    }
+
+    let consensus_state_id = msg.consensus_state_id;
+    let consensus_client_id = host
+        .consensus_client_id(consensus_state_id)
+        .ok_or(Error::ConsensusStateIdNotRecognized { consensus_state_id })?;
     let consensus_client = host.consensus_client(consensus_client_id)?;
-    let trusted_state = host.consensus_state(msg.consensus_state_id)?;
+    let now = now_from_host(host);
+    let update_time = check_update_time(host, consensus_state_id, now)?;
 
-    let update_time = host.consensus_update_time(msg.consensus_state_id)?;
-    let delay = host.challenge_period(msg.consensus_state_id).ok_or(
-        Error::ChallengePeriodNotConfigured { consensus_state_id: msg.consensus_state_id },
-    )?;
-    let now = host.timestamp();
+    let pending = host
+        .optimistic_consensus_updates()
+        .then(|| host.pending_consensus_update(consensus_state_id))
+        .flatten();
+    let (trusted_state, last_proof) = match pending {
+        Some(pending) => {
+            check_challenge_period(host, consensus_state_id, now, pending.submitted_at)?;
+            (pending.consensus_state, Some(pending.proof_hash))
+        }
+        None => {
+            if !host.optimistic_consensus_updates() {
+                check_challenge_period(host, consensus_state_id, now, update_time)?;
+            }
+            host.is_expired(consensus_state_id)?;
+            (
+                host.consensus_state(consensus_state_id)?,
+                host.last_consensus_proof(consensus_state_id),
+            )
+        }
+    };
+
+    match verify_update(host, &*consensus_client, msg, trusted_state, last_proof, now)? {
+        VerifiedUpdate::Update(_) => Ok(()),
+        VerifiedUpdate::Byzantine(_) => Err(Error::ByzantineBehaviour { consensus_state_id }),
+    }
+}
+
+/// Ensures a consensus state can be updated at `now`, returning the time of its last update
+fn check_update_time<H>(
+    host: &H,
+    consensus_state_id: ConsensusStateId,
+    now: Duration,
+) -> Result<Duration, Error>
+where
+    H: IsmpHost,
+{
+    let update_time = host.consensus_update_time(consensus_state_id)?;
+    host.is_consensus_client_frozen(consensus_state_id)?;
 
-    host.is_consensus_client_frozen(msg.consensus_state_id)?;
+    // The host clock must never run behind the last update, or the update time would move
+    // backwards and corrupt the challenge period
+    if now < update_time {
+        Err(Error::NonMonotonicClock { consensus_state_id, update_time, current_time: now })?
+    }
 
-    if (now - update_time) <= delay {
+    Ok(update_time)
+}
+
+/// Reject new updates while the previous one is still within its challenge period, so that its
+/// update time can't be overwritten before fraud proofs against it can be submitted.
+fn check_challenge_period<H>(
+    host: &H,
+    consensus_state_id: ConsensusStateId,
+    now: Duration,
+    update_time: Duration,
+) -> Result<(), Error>
+where
+    H: IsmpHost,
+{
+    let delay = host
+        .challenge_period(consensus_state_id)
+        .ok_or(Error::ChallengePeriodNotConfigured { consensus_state_id })?;
+    if now.saturating_sub(update_time) <= delay {
         Err(Error::ChallengePeriodNotElapsed {
-            consensus_state_id: msg.consensus_state_id,
+            consensus_state_id,
             current_time: now,
             update_time,
         })?
     }
 
-    host.is_expired(msg.consensus_state_id)?;
+    Ok(())
+}
+
+/// The result of verifying a consensus message against the trusted consensus state
+enum VerifiedUpdate {
+    /// The update passed every check and can be stored
+    Update(PendingConsensusUpdate),
+    /// The proof revealed byzantine behaviour, with the evidence of it
+    Byzantine(Vec<u8>),
+}
 
-    let (new_state, intermediate_states) = consensus_client.verify_consensus(
+/// Verify a consensus message against the trusted consensus state and check the update it
+/// produces. `last_proof` is the hash of the last applied consensus proof.
+fn verify_update<H>(
+    host: &H,
+    consensus_client: &dyn ConsensusClient,
+    msg: ConsensusMessage,
+    trusted_state: Vec<u8>,
+    last_proof: Option<H256>,
+    now: Duration,
+) -> Result<VerifiedUpdate, Error>
+where
+    H: IsmpHost,
+{
+    let consensus_state_id = msg.consensus_state_id;
+    if let Some(expected) = msg.expected_trusted_state {
+        let found = H::keccak256(&trusted_state);
+        if found != expected {
+            Err(Error::TrustedStateMismatch { consensus_state_id, expected, found })?
+        }
+    }
+
+    // Retried submissions of an already applied proof are rejected without verifying them again
+    let proof_hash = H::keccak256(&(consensus_state_id, &msg.consensus_proof).encode());
+    if last_proof == Some(proof_hash) {
+        Err(Error::DuplicateConsensusProof { consensus_state_id })?
+    }
+
+    let trusted_height = consensus_client.consensus_state_height(&trusted_state)?;
+    let outcome = consensus_client.verify_consensus(
         host,
-        msg.consensus_state_id,
+        consensus_state_id,
         trusted_state,
         msg.consensus_proof,
     )?;
-    host.store_consensus_state(msg.consensus_state_id, new_state)?;
-    let timestamp = host.timestamp();
-    host.store_consensus_update_time(msg.consensus_state_id, timestamp)?;
+    let (new_state, mut intermediate_states, rolled_back) = match outcome {
+        VerifyOutcome::Verified(new_state, intermediate_states) => {
+            (new_state, intermediate_states, false)
+        }
+        VerifyOutcome::RolledBack(new_state, intermediate_states) => {
+            (new_state, intermediate_states, true)
+        }
+        VerifyOutcome::Byzantine(evidence) => return Ok(VerifiedUpdate::Byzantine(evidence)),
+    };
+    // Don't trust the client to have advanced its own state
+    if let (Some(trusted_height), Some(new_height)) =
+        (trusted_height, consensus_client.consensus_state_height(&new_state)?)
+    {
+        if new_height <= trusted_height {
+            Err(Error::ConsensusNotAdvanced { consensus_state_id, trusted_height, new_height })?
+        }
+    }
+    for (state_id, commitment_heights) in intermediate_states.iter_mut() {
+        // Sort by height and break ties by the commitment, so that every host processing this
+        // message stores the same commitments regardless of the order they were returned in.
+        commitment_heights.sort_unstable_by_key(|a| (a.height, a.commitment));
+        // Commitment timestamps must not decrease as the height increases
+        if let Some(pair) = commitment_heights
+            .windows(2)
+            .find(|pair| pair[1].commitment.timestamp < pair[0].commitment.timestamp)
+        {
+            Err(Error::NonMonotonicCommitmentTimestamp {
+                height: StateMachineHeight {
+                    id: StateMachineId { state_id: *state_id, consensus_state_id },
+                    height: pair[1].height,
+                },
+            })?
+        }
+    }
+
+    Ok(VerifiedUpdate::Update(PendingConsensusUpdate {
+        consensus_state: new_state,
+        commitments: intermediate_states,
+        proof_hash,
+        submitted_at: now,
+        rolled_back,
+    }))
+}
+
+/// Promote the pending update of a consensus state once its challenge period has elapsed, on hosts
+/// that apply consensus updates optimistically. Returns `None` if there is no pending update or
+/// its challenge period has not yet elapsed. Pending updates are also promoted by the next
+/// consensus message, hosts may call this once per block so they don't wait on a relayer.
+pub fn promote_pending_update<H>(
+    host: &H,
+    consensus_state_id: ConsensusStateId,
+) -> Result<Option<ConsensusUpdateResult>, Error>
+where
+    H: IsmpHost,
+{
+    let Some(pending) = host.pending_consensus_update(consensus_state_id) else { return Ok(None) };
+    let consensus_client_id = host
+        .consensus_client_id(consensus_state_id)
+        .ok_or(Error::ConsensusStateIdNotRecognized { consensus_state_id })?;
+    // A fraud proof against the pending update freezes the client, so it is never promoted
+    host.is_consensus_client_frozen(consensus_state_id)?;
+    let delay = host
+        .challenge_period(consensus_state_id)
+        .ok_or(Error::ChallengePeriodNotConfigured { consensus_state_id })?;
+    if now_from_host(host).saturating_sub(pending.submitted_at) <= delay {
+        return Ok(None)
+    }
+
+    host.store_pending_consensus_update(consensus_state_id, None)?;
+    // The commitments have already waited out the challenge period while pending
+    apply_update(host, consensus_client_id, consensus_state_id, pending).map(Some)
+}
+
+/// Store a verified consensus update and the state commitments it finalized, treating them as
+/// updated at the time the update was submitted
+fn apply_update<H>(
+    host: &H,
+    consensus_client_id: ConsensusClientId,
+    consensus_state_id: ConsensusStateId,
+    update: PendingConsensusUpdate,
+) -> Result<ConsensusUpdateResult, Error>
+where
+    H: IsmpHost,
+{
+    let PendingConsensusUpdate {
+        consensus_state: new_state,
+        commitments: intermediate_states,
+        proof_hash,
+        submitted_at: update_time,
+        rolled_back,
+    } = update;
+    let delay = host
+        .challenge_period(consensus_state_id)
+        .ok_or(Error::ChallengePeriodNotConfigured { consensus_state_id })?;
+    // Commitments updated before this are past their challenge period and final
+    let finalized_before = now_from_host(host).saturating_sub(delay);
+    host.store_consensus_state(consensus_state_id, new_state)?;
+    host.store_consensus_update_time(consensus_state_id, update_time)?;
+    host.store_last_consensus_proof(consensus_state_id, proof_hash);
+    // A scheduled unbonding period only applies from this update onwards, the previous window
+    // was already checked against the old period
+    if let Some(period) = host.pending_unbonding_period(consensus_state_id) {
+        host.store_unbonding_period(consensus_state_id, period)?;
+        host.store_pending_unbonding_period(consensus_state_id, None)?;
+    }
     let mut state_updates = BTreeSet::new();
-    for (id, mut commitment_heights) in intermediate_states {
-        commitment_heights.sort_unstable_by(|a, b| a.height.cmp(&b.height));
-        let id = StateMachineId { state_id: id, consensus_state_id: msg.consensus_state_id };
-        let previous_latest_height = host.latest_commitment_height(id)?;
+    let mut commitments = Vec::new();
+    for (id, commitment_heights) in intermediate_states {
+        let id = StateMachineId { state_id: id, consensus_state_id };
+        // A state machine without a stored height accepts commitments at any height
+        let previous_latest_height = match host.latest_commitment_height(id) {
+            Ok(height) => Some(height),
+            Err(Error::NoCommitmentHeight { .. }) => None,
+            Err(e) => Err(e)?,
+        };
+        let mut min_height = previous_latest_height;
+        // The client rolled back to another fork, so the commitments it replaces are no longer
+        // valid unless they are already final. They are invalidated before the new fork's
+        // commitments are stored, so that the new fork replaces them at the heights both share.
+        if let (true, Some(previous), Some(first)) =
+            (rolled_back, previous_latest_height, commitment_heights.first())
+        {
+            if first.height <= previous {
+                let fork_height = first.height.saturating_sub(1);
+                host.invalidate_commitments_above(id, fork_height, finalized_before)?;
+                // Final commitments are kept, and the new fork can't replace them
+                if host
+                    .state_machine_commitment(StateMachineHeight { id, height: previous })
+                    .is_err()
+                {
+                    min_height = Some(fork_height);
+                }
+            }
+        }
         for commitment_height in commitment_heights.iter() {
             let state_height = StateMachineHeight { id, height: commitment_height.height };
             // If a state machine is frozen, we skip it
@@ -75,34 +373,49 @@ where
             }
 
             // Only allow heights greater than latest height
-            if previous_latest_height > commitment_height.height {
+            if min_height.is_some_and(|latest| latest > commitment_height.height) {
                 continue
             }
 
-            // Skip duplicate states
-            if host.state_machine_commitment(state_height).is_ok() {
+            // Skip duplicate states, including those already queued in this update
+            if host.state_machine_commitment(state_height).is_ok() ||
+                commitments.last().map(|(height, _)| *height) == Some(state_height)
+            {
                 continue
             }
 
-            host.store_state_machine_commitment(state_height, commitment_height.commitment)?;
-            host.store_state_machine_update_time(state_height, host.timestamp())?;
+            host.store_state_machine_update_time(state_height, update_time)?;
+            commitments.push((state_height, commitment_height.commitment));
         }
 
-        if let Some(latest_height) = commitment_heights.last() {
-            let latest_height = StateMachineHeight { id, height: latest_height.height };
-            state_updates
-                .insert((StateMachineHeight { id, height: previous_latest_height }, latest_height));
-            host.store_latest_commitment_height(latest_height)?;
+        let Some(latest_height) = commitment_heights.last() else { continue };
+        let latest_height = StateMachineHeight { id, height: latest_height.height };
+        if let Some(previous) =
+            previous_latest_height.filter(|previous| latest_height.height <= *previous)
+        {
+            // Stale updates are ignored, the latest height is only lowered by a rollback that
+            // invalidated its commitment
+            let previous = StateMachineHeight { id, height: previous };
+            if !rolled_back || host.state_machine_commitment(previous).is_ok() {
+                continue
+            }
         }
+        // A state machine updated for the first time is reported as moving up from height zero
+        let previous_height =
+            StateMachineHeight { id, height: previous_latest_height.unwrap_or_default() };
+        state_updates.insert((previous_height, latest_height));
+        host.store_latest_commitment_height(latest_height)?;
     }
 
-    let result = ConsensusUpdateResult {
+    // Store all commitments in a single write
+    host.store_state_machine_commitments_batch(commitments.clone())?;
+
+    Ok(ConsensusUpdateResult {
         consensus_client_id,
-        consensus_state_id: msg.consensus_state_id,
+        consensus_state_id,
         state_updates,
-    };
-
-    Ok(MessageResult::ConsensusMessage(result))
+        commitments,
+    })
 }
 
 /// Handles the creation of consensus clients
@@ -113,13 +426,32 @@ pub fn create_client<H>(
 where
     H: IsmpHost,
 {
+    message.validate()?;
+
     // check that we have an implementation of this client
     host.consensus_client(message.consensus_client_id)?;
 
+    if !host.is_client_type_allowed(message.consensus_client_id) {
+        Err(Error::ClientTypeNotAllowed { id: message.consensus_client_id })?
+    }
+
     if host.consensus_client_id(message.consensus_state_id).is_some() {
         Err(Error::DuplicateConsensusStateId { consensus_state_id: message.consensus_state_id })?
     }
 
+    // Catch clients registered for state machines they can't track
+    if let Some((id, _)) = message
+        .state_machine_commitments
+        .iter()
+        .find(|(id, _)| !host.is_expected_client_type(id.state_id, message.consensus_client_id))
+    {
+        Err(Error::UnexpectedClientType {
+            state_machine: id.state_id,
+            expected: id.state_id.default_consensus_client(),
+            got: message.consensus_client_id,
+        })?
+    }
+
     // Store the initial state for the consensus client
     host.store_consensus_state(message.consensus_state_id, message.consensus_state)?;
     host.store_unbonding_period(message.consensus_state_id, message.unbonding_period)?;
@@ -130,11 +462,11 @@ where
     for (id, state_commitment) in message.state_machine_commitments {
         let height = StateMachineHeight { id, height: state_commitment.height };
         host.store_state_machine_commitment(height, state_commitment.commitment)?;
-        host.store_state_machine_update_time(height, host.timestamp())?;
+        host.store_state_machine_update_time(height, now_from_host(host))?;
         host.store_latest_commitment_height(height)?;
     }
 
-    host.store_consensus_update_time(message.consensus_state_id, host.timestamp())?;
+    host.store_consensus_update_time(message.consensus_state_id, now_from_host(host))?;
 
     Ok(ConsensusClientCreatedResult {
         consensus_client_id: message.consensus_client_id,
@@ -143,21 +475,113 @@ where
 }
 
 /// Freeze a consensus client by providing a valid fraud proof.
-pub fn freeze_client<H>(host: &H, msg: FraudProofMessage) -> Result<MessageResult, Error>
+pub(crate) fn freeze_client<H>(
+    host: &H,
+    msg: FraudProofMessage,
+    clients: &ClientCache,
+) -> Result<MessageResult, Error>
 where
     H: IsmpHost,
 {
     let consensus_client_id = host
         .consensus_client_id(msg.consensus_state_id)
         .ok_or_else(|| Error::ImplementationSpecific("Unknown Consensus State Id".to_string()))?;
-    let consensus_client = host.consensus_client(consensus_client_id)?;
+    let consensus_client = clients.get(host, consensus_client_id)?;
     let trusted_state = host.consensus_state(msg.consensus_state_id)?;
 
     consensus_client.verify_fraud_proof(host, trusted_state, msg.proof_1, msg.proof_2)?;
 
     host.freeze_consensus_client(msg.consensus_state_id)?;
 
-    host.store_consensus_update_time(msg.consensus_state_id, host.timestamp())?;
+    host.store_consensus_update_time(msg.consensus_state_id, now_from_host(host))?;
 
     Ok(MessageResult::FrozenClient(msg.consensus_state_id))
 }
+
+/// Unfreeze a state machine, this must be authorized by the host's governance authority.
+/// `origin` is the account that submitted the message, which the host must have authenticated,
+/// e.g. from the signed origin of the transaction.
+pub fn unfreeze_state_machine<H>(
+    host: &H,
+    origin: &[u8],
+    msg: UnfreezeStateMachineMessage,
+) -> Result<StateMachineUnfrozen, Error>
+where
+    H: IsmpHost,
+{
+    if !host.is_governance_authority(origin) {
+        Err(Error::Unauthorized { account: origin.to_vec() })?
+    }
+
+    host.unfreeze_state_machine(msg.height)?;
+
+    let event = StateMachineUnfrozen {
+        height: msg.height,
+        authority: origin.to_vec(),
+        timestamp: now_from_host(host).as_secs(),
+    };
+    host.emit_state_machine_unfrozen(event.clone());
+
+    Ok(event)
+}
+
+/// Schedule a new unbonding period for a consensus state, this must be authorized by the host's
+/// governance authority. The period is applied on the next consensus update. `origin` is the
+/// account that submitted the message, which the host must have authenticated.
+pub fn set_unbonding_period<H>(
+    host: &H,
+    origin: &[u8],
+    msg: SetUnbondingPeriodMessage,
+) -> Result<(), Error>
+where
+    H: IsmpHost,
+{
+    if !host.is_governance_authority(origin) {
+        Err(Error::Unauthorized { account: origin.to_vec() })?
+    }
+
+    host.consensus_client_id(msg.consensus_state_id).ok_or(
+        Error::ConsensusStateIdNotRecognized { consensus_state_id: msg.consensus_state_id },
+    )?;
+    host.store_pending_unbonding_period(msg.consensus_state_id, Some(msg.new_period))
+}
+
+/// Drain the state machine heights whose challenge period has elapsed through
+/// [`IsmpHost::drain_elapsed_commitments`] and acknowledge each of them to peer hosts. Hosts
+/// gossiping finality should call this once per block in place of draining them directly.
+pub fn acknowledge_finalized_commitments<H>(host: &H) -> Result<Vec<ConsensusAckMessage>, Error>
+where
+    H: IsmpHost,
+{
+    host.drain_elapsed_commitments(now_from_host(host))
+        .into_iter()
+        .map(|height| {
+            let commitment = host.state_machine_commitment(height)?;
+            let ack = ConsensusAckMessage {
+                consensus_state_id: height.id.consensus_state_id,
+                height,
+                commitment_root: commitment.state_root,
+            };
+            host.emit_consensus_ack(ack);
+            Ok(ack)
+        })
+        .collect()
+}
+
+/// Record an acknowledgement of a finalized height received from a peer host. `origin` is the
+/// account that submitted the acknowledgement, which the host must have authenticated as one of
+/// its peers.
+pub fn record_consensus_ack<H>(
+    host: &H,
+    origin: &[u8],
+    ack: ConsensusAckMessage,
+) -> Result<(), Error>
+where
+    H: IsmpHost,
+{
+    if !host.is_consensus_peer(origin) {
+        Err(Error::Unauthorized { account: origin.to_vec() })?
+    }
+
+    host.store_consensus_ack(ack)
+}
@@ -16,27 +16,92 @@
 //! The ISMP request handler
 
 use crate::{
+    consensus::StateMachineClient,
     error::Error,
-    handlers::{validate_state_machine, MessageResult},
+    events::{DeliveryOutcome, TraceEvent, TraceStage},
+    handlers::{
+        check_homogeneous_batch, check_known_source, validate_state_machine,
+        verify_membership_cached, ClientCache, MessageResult,
+    },
     host::{IsmpHost, StateMachine},
     messaging::RequestMessage,
-    module::{DispatchError, DispatchSuccess},
-    router::{Request, RequestResponse},
+    module::{DispatchError, DispatchSuccess, ModuleId},
+    router::{Request, RequestResponseRef, MAX_POST_META_LEN, REQUEST_VERSION},
+    util::{hash_request, now_from_host},
 };
-use alloc::{format, vec::Vec};
+use alloc::{boxed::Box, format, vec::Vec};
+
+/// Checks a request message before its proof is verified, shared by the request handler and
+/// [`crate::verify::verify_request_membership`]. Returns the client for the proof's state
+/// machine, or `None` if the host's unknown state machine policy drops the batch.
+pub(crate) fn validate_request_message<H>(
+    host: &H,
+    msg: &RequestMessage,
+    clients: &ClientCache,
+) -> Result<Option<Box<dyn StateMachineClient>>, Error>
+where
+    H: IsmpHost,
+{
+    if msg.requests.is_empty() {
+        Err(Error::EmptyMessage)?
+    }
+
+    // Requests must not be addressed to the same chain they originate from
+    if let Some(request) = msg.requests.iter().find(|req| req.source == req.dest) {
+        Err(Error::SelfDispatch { state_machine: request.source })?
+    }
+
+    if let Some(request) = msg.requests.iter().find(|req| req.version > REQUEST_VERSION) {
+        Err(Error::UnsupportedRequestVersion { nonce: request.nonce, version: request.version })?
+    }
+
+    if let Some(meta) = msg
+        .requests
+        .iter()
+        .filter_map(|req| req.meta.as_ref())
+        .find(|meta| meta.len() > MAX_POST_META_LEN)
+    {
+        Err(Error::MetadataTooLarge { len: meta.len(), max: MAX_POST_META_LEN })?
+    }
+
+    check_homogeneous_batch(msg.requests.iter().map(|req| (req.nonce, req.source, req.dest)))?;
+
+    // Requests must be addressed to this host
+    let expected = host.host_state_machine();
+    if let Some(request) = msg.requests.iter().find(|req| req.dest != expected) {
+        Err(Error::WrongDestination { expected, got: request.dest })?
+    }
+
+    let state_machine = validate_state_machine(host, &msg.proof, clients)?;
+
+    // The batch is homogeneous, so it is accepted or dropped as a whole
+    if !check_known_source(host, msg.requests[0].source, msg.proof.height.id.state_id)? {
+        return Ok(None)
+    }
+
+    Ok(Some(state_machine))
+}
 
 /// Validate the state machine, verify the request message and dispatch the message to the router
-pub fn handle<H>(host: &H, msg: RequestMessage) -> Result<MessageResult, Error>
+pub(crate) fn handle<H>(
+    host: &H,
+    msg: RequestMessage,
+    clients: &ClientCache,
+) -> Result<MessageResult, Error>
 where
     H: IsmpHost,
 {
-    let state_machine = validate_state_machine(host, msg.proof.height)?;
+    let Some(state_machine) = validate_request_message(host, &msg, clients)? else {
+        return Ok(MessageResult::Request(Vec::new()))
+    };
     // Verify membership proof
     let state = host.state_machine_commitment(msg.proof.height)?;
 
-    state_machine.verify_membership(
+    let requests = msg.requests.iter().cloned().map(Request::Post).collect::<Vec<_>>();
+    verify_membership_cached(
         host,
-        RequestResponse::Request(msg.requests.clone().into_iter().map(Request::Post).collect()),
+        &*state_machine,
+        RequestResponseRef::Request(&requests),
         state,
         &msg.proof,
     )?;
@@ -57,13 +122,25 @@ where
                 check_source(req.source_chain())
         })
         .map(|request| {
-            let cb = router.module_for_id(request.to.clone())?;
+            let module_id = ModuleId::from(request.to.clone());
+            if !host.is_registered_module(&module_id) {
+                return Ok(Err(DispatchError {
+                    msg: format!("{:?}", Error::UnknownModule { id: module_id }),
+                    nonce: request.nonce,
+                    source_chain: request.source,
+                    dest_chain: request.dest,
+                }))
+            }
+
+            let cb = router.module_for_id(module_id.0)?;
             let res = cb
                 .on_accept(request.clone())
                 .map(|_| DispatchSuccess {
                     dest_chain: request.dest,
                     source_chain: request.source,
                     nonce: request.nonce,
+                    late: request.is_late(state.timestamp()),
+                    gas_used: 0,
                 })
                 .map_err(|e| DispatchError {
                     msg: format!("{e:?}"),
@@ -71,8 +148,21 @@ where
                     source_chain: request.source,
                     dest_chain: request.dest,
                 });
-            if res.is_ok() {
-                host.store_request_receipt(&Request::Post(request))?;
+            let request = Request::Post(request);
+            let commitment = hash_request::<H>(&request);
+            match &res {
+                Ok(_) => {
+                    host.store_request_receipt(&request)?;
+                    host.trace(TraceEvent {
+                        commitment,
+                        stage: TraceStage::Delivered,
+                        timestamp: now_from_host(host).as_secs(),
+                    });
+                    host.record_delivery(commitment, DeliveryOutcome::Delivered);
+                }
+                Err(e) => {
+                    host.record_delivery(commitment, DeliveryOutcome::ModuleRejected(e.msg.clone()))
+                }
             }
             Ok(res)
         })
@@ -16,27 +16,68 @@
 //! The ISMP response handler
 
 use crate::{
+    consensus::StateProof,
     error::Error,
-    handlers::{validate_state_machine, MessageResult},
+    handlers::{
+        check_get_keys, check_homogeneous_batch, check_proof_values, validate_state_machine,
+        verify_membership_cached, ClientCache, MessageResult,
+    },
     host::IsmpHost,
-    messaging::{sufficient_proof_height, ResponseMessage},
+    messaging::{sufficient_proof_height, Proof, ResponseMessage},
     module::{DispatchError, DispatchSuccess},
-    router::{GetResponse, RequestResponse, Response},
+    router::{RequestResponseRef, Response},
     util::hash_request,
 };
 use alloc::{format, string::ToString, vec::Vec};
 
 /// Validate the state machine, verify the response message and dispatch the message to the router
-pub fn handle<H>(host: &H, msg: ResponseMessage) -> Result<MessageResult, Error>
+pub(crate) fn handle<H>(
+    host: &H,
+    msg: ResponseMessage,
+    clients: &ClientCache,
+) -> Result<MessageResult, Error>
 where
     H: IsmpHost,
 {
-    let state_machine = validate_state_machine(host, msg.proof().height)?;
+    let is_empty = match &msg {
+        ResponseMessage::Post { responses, .. } => responses.is_empty(),
+        ResponseMessage::Get { requests, .. } => requests.is_empty(),
+    };
+    if is_empty {
+        Err(Error::EmptyMessage)?
+    }
+
+    match &msg {
+        ResponseMessage::Post { responses, .. } => {
+            check_homogeneous_batch(
+                responses.iter().map(|res| (res.nonce(), res.source_chain(), res.dest_chain())),
+            )?;
+            // Fire-and-forget requests are never responded to
+            if let Some(response) = responses
+                .iter()
+                .find(|res| matches!(res, Response::Post(res) if !res.post.expects_response))
+            {
+                Err(Error::UnexpectedResponse {
+                    nonce: response.nonce(),
+                    source: response.source_chain(),
+                    dest: response.dest_chain(),
+                })?
+            }
+        }
+        ResponseMessage::Get { requests, .. } => {
+            check_homogeneous_batch(
+                requests.iter().map(|req| (req.nonce(), req.source_chain(), req.dest_chain())),
+            )?;
+            check_get_keys(host, requests)?;
+        }
+    }
+
+    let state_machine = validate_state_machine(host, msg.proof(), clients)?;
 
     let state = host.state_machine_commitment(msg.proof().height)?;
 
     let result = match msg {
-        ResponseMessage::Post { responses, proof } => {
+        ResponseMessage::Post { responses, proof, receipt_proof } => {
             // For a response to be valid a request commitment must be present in storage
             // Also we must not have received a response for this request
             let responses = responses
@@ -49,12 +90,19 @@ where
                 })
                 .collect::<Vec<_>>();
             // Verify membership proof
-            state_machine.verify_membership(
+            verify_membership_cached(
                 host,
-                RequestResponse::Response(responses.clone()),
+                &*state_machine,
+                RequestResponseRef::Response(&responses),
                 state,
                 &proof,
             )?;
+            // Hosts that require it check the source received the requests it is responding to
+            if host.requires_response_binding() {
+                let receipt_proof = receipt_proof.ok_or(Error::MissingReceiptProof)?;
+                let receipt_proof = Proof { height: proof.height, proof: receipt_proof };
+                state_machine.verify_response_binding(host, &responses, state, &receipt_proof)?;
+            }
 
             let router = host.ismp_router();
 
@@ -68,6 +116,8 @@ where
                             dest_chain: response.dest_chain(),
                             source_chain: response.source_chain(),
                             nonce: response.nonce(),
+                            late: false,
+                            gas_used: 0,
                         })
                         .map_err(|e| DispatchError {
                             msg: format!("{e:?}"),
@@ -100,19 +150,26 @@ where
                     let keys = request.keys().ok_or_else(|| {
                         Error::ImplementationSpecific("Missing keys for get request".to_string())
                     })?;
-                    let values = state_machine.verify_state_proof(host, keys, state, &proof)?;
+                    let state_proof = StateProof { keys: keys.clone(), height: proof.height };
+                    let (values, metadata) = state_machine.verify_state_proof_with_metadata(
+                        host,
+                        state_proof,
+                        state,
+                        &proof,
+                    )?;
+                    check_proof_values(&keys, &values)?;
+                    host.record_proof_metadata(hash_request::<H>(&request), metadata);
 
                     let router = host.ismp_router();
                     let cb = router.module_for_id(request.source_module())?;
                     let res = cb
-                        .on_response(Response::Get(GetResponse {
-                            get: request.get_request()?,
-                            values,
-                        }))
+                        .on_response(Response::Get(request.get_request()?.respond(values)))
                         .map(|_| DispatchSuccess {
                             dest_chain: request.dest_chain(),
                             source_chain: request.source_chain(),
                             nonce: request.nonce(),
+                            late: false,
+                            gas_used: 0,
                         })
                         .map_err(|e| DispatchError {
                             msg: format!("{e:?}"),
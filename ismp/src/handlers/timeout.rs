@@ -16,111 +16,162 @@
 //! The ISMP request timeout handler
 
 use crate::{
+    consensus::StateProof,
     error::Error,
-    handlers::{validate_state_machine, MessageResult},
+    events::{TraceEvent, TraceStage},
+    handlers::{check_proof_values, validate_state_machine, ClientCache, MessageResult},
     host::IsmpHost,
     messaging::TimeoutMessage,
-    module::{DispatchError, DispatchSuccess},
-    util::hash_request,
+    module::{DispatchError, DispatchResult, DispatchSuccess},
+    router::{IsmpRouter, Request},
+    util::{hash_request, now_from_host},
 };
-use alloc::{format, vec::Vec};
+use alloc::{format, string::ToString, vec, vec::Vec};
+use core::time::Duration;
 
-/// This function handles timeouts for Requests
-pub fn handle<H>(host: &H, msg: TimeoutMessage) -> Result<MessageResult, Error>
+/// This function handles timeouts for Requests. Requests in the batch are checked individually,
+/// so a request that has not timed out is rejected without affecting the rest of the batch.
+pub(crate) fn handle<H>(
+    host: &H,
+    msg: TimeoutMessage,
+    clients: &ClientCache,
+) -> Result<MessageResult, Error>
 where
     H: IsmpHost,
 {
+    let router = host.ismp_router();
     let results = match msg {
         TimeoutMessage::Post { requests, timeout_proof } => {
-            let state_machine = validate_state_machine(host, timeout_proof.height)?;
+            let state_machine = validate_state_machine(host, &timeout_proof, clients)?;
             let state = host.state_machine_commitment(timeout_proof.height)?;
-            for request in &requests {
-                // Ensure a commitment exists for all requests in the batch
-                let commitment = hash_request::<H>(request);
-                host.request_commitment(commitment)?;
-
-                if !request.timed_out(state.timestamp()) {
-                    Err(Error::RequestTimeoutNotElapsed {
-                        nonce: request.nonce(),
-                        source: request.source_chain(),
-                        dest: request.dest_chain(),
-                        timeout_timestamp: request.timeout(),
-                        state_machine_time: state.timestamp(),
-                    })?
-                }
-            }
-
-            let key = state_machine.state_trie_key(requests.clone());
-
-            let values = state_machine.verify_state_proof(host, key, state, &timeout_proof)?;
-
-            if values.into_iter().any(|(_key, val)| val.is_some()) {
-                Err(Error::ImplementationSpecific("Some Requests not timed out".into()))?
-            }
-
-            let router = host.ismp_router();
-            requests
+            let checked = requests
                 .into_iter()
                 .map(|request| {
-                    let cb = router.module_for_id(request.source_module())?;
-                    let res = cb
-                        .on_timeout(request.clone())
-                        .map(|_| DispatchSuccess {
-                            dest_chain: request.dest_chain(),
-                            source_chain: request.source_chain(),
-                            nonce: request.nonce(),
-                        })
-                        .map_err(|e| DispatchError {
-                            msg: format!("{e:?}"),
-                            nonce: request.nonce(),
-                            source_chain: request.source_chain(),
-                            dest_chain: request.dest_chain(),
-                        });
-                    host.delete_request_commitment(&request)?;
-                    Ok(res)
+                    let keys = check_timeout(host, &request, state.timestamp())
+                        .map(|_| state_machine.state_trie_key(vec![request.clone()]));
+                    (request, keys)
                 })
-                .collect::<Result<Vec<_>, _>>()?
-        }
-        TimeoutMessage::Get { requests } => {
-            for request in &requests {
-                let commitment = hash_request::<H>(request);
-                host.request_commitment(commitment)?;
+                .collect::<Vec<_>>();
 
-                // Ensure the get timeout has elapsed on the host
-                if !request.timed_out(host.timestamp()) {
-                    Err(Error::RequestTimeoutNotElapsed {
-                        nonce: request.nonce(),
-                        source: request.source_chain(),
-                        dest: request.dest_chain(),
-                        timeout_timestamp: request.timeout(),
-                        state_machine_time: host.timestamp(),
-                    })?
-                }
-            }
-            let router = host.ismp_router();
-            requests
+            // A single non-membership proof covers every request that passed the checks
+            let keys = checked
+                .iter()
+                .filter_map(|(_, keys)| keys.as_ref().ok())
+                .flatten()
+                .cloned()
+                .collect::<Vec<_>>();
+            let values = if keys.is_empty() {
+                Default::default()
+            } else {
+                let state_proof = StateProof { keys: keys.clone(), height: timeout_proof.height };
+                let values =
+                    state_machine.verify_state_proof(host, state_proof, state, &timeout_proof)?;
+                check_proof_values(&keys, &values)?;
+                values
+            };
+
+            checked
                 .into_iter()
-                .map(|request| {
-                    let cb = router.module_for_id(request.source_module())?;
-                    let res = cb
-                        .on_timeout(request.clone())
-                        .map(|_| DispatchSuccess {
-                            dest_chain: request.dest_chain(),
-                            source_chain: request.source_chain(),
-                            nonce: request.nonce(),
-                        })
-                        .map_err(|e| DispatchError {
-                            msg: format!("{e:?}"),
-                            nonce: request.nonce(),
-                            source_chain: request.source_chain(),
-                            dest_chain: request.dest_chain(),
-                        });
-                    host.delete_request_commitment(&request)?;
-                    Ok(res)
+                .map(|(request, keys)| {
+                    let verified = keys.and_then(|keys| {
+                        // The request must not have been received on the destination
+                        if keys.iter().any(|key| matches!(values.get(key), Some(Some(_)))) {
+                            Err(Error::RequestTimeoutVerificationFailed {
+                                nonce: request.nonce(),
+                                source: request.source_chain(),
+                                dest: request.dest_chain(),
+                            })?
+                        }
+                        Ok(())
+                    });
+                    dispatch_timeout(host, &*router, request, verified)
                 })
                 .collect::<Result<Vec<_>, _>>()?
         }
+        TimeoutMessage::Get { requests } => requests
+            .into_iter()
+            .map(|request| {
+                // Ensure the get timeout has elapsed on the host
+                let verified = check_timeout(host, &request, now_from_host(host));
+                dispatch_timeout(host, &*router, request, verified)
+            })
+            .collect::<Result<Vec<_>, _>>()?,
     };
 
     Ok(MessageResult::Timeout(results))
 }
+
+/// Ensures a commitment exists for the request and that it has timed out at the given timestamp
+fn check_timeout<H>(host: &H, request: &Request, timestamp: Duration) -> Result<(), Error>
+where
+    H: IsmpHost,
+{
+    let commitment = hash_request::<H>(request);
+    host.request_commitment(commitment)?;
+
+    if !request.timed_out(timestamp) {
+        Err(Error::RequestTimeoutNotElapsed {
+            nonce: request.nonce(),
+            source: request.source_chain(),
+            dest: request.dest_chain(),
+            timeout_timestamp: request.timeout(),
+            state_machine_time: timestamp,
+        })?
+    }
+
+    Ok(())
+}
+
+/// Notifies the source module of a timed-out request and deletes its commitment. Requests that
+/// failed verification are rejected without being dispatched, and the commitment is retained so
+/// that the timeout can be retried if the module asked for a retry or did not roll back the
+/// request.
+fn dispatch_timeout<H>(
+    host: &H,
+    router: &dyn IsmpRouter,
+    request: Request,
+    verified: Result<(), Error>,
+) -> Result<DispatchResult, Error>
+where
+    H: IsmpHost,
+{
+    let dispatch_error = |e: Error| DispatchError {
+        msg: format!("{e:?}"),
+        nonce: request.nonce(),
+        source_chain: request.source_chain(),
+        dest_chain: request.dest_chain(),
+    };
+    if let Err(e) = verified {
+        return Ok(Err(dispatch_error(e)))
+    }
+
+    let cb = router.module_for_id(request.source_module())?;
+    let res = match cb.on_timeout(request.clone()) {
+        Ok(outcome) if outcome.rolled_back => Ok(DispatchSuccess {
+            dest_chain: request.dest_chain(),
+            source_chain: request.source_chain(),
+            nonce: request.nonce(),
+            late: false,
+            gas_used: outcome.gas_used,
+        }),
+        // Retain the commitment so the timeout can be retried
+        Ok(_) => {
+            return Ok(Err(DispatchError {
+                msg: "Module did not roll back the timed-out request".to_string(),
+                nonce: request.nonce(),
+                source_chain: request.source_chain(),
+                dest_chain: request.dest_chain(),
+            }))
+        }
+        Err(e @ Error::RetryTimeout) => return Ok(Err(dispatch_error(e))),
+        // Any other failure is final
+        Err(e) => Err(dispatch_error(e)),
+    };
+    host.delete_request_commitment(&request)?;
+    host.trace(TraceEvent {
+        commitment: hash_request::<H>(&request),
+        stage: TraceStage::TimedOut,
+        timestamp: now_from_host(host).as_secs(),
+    });
+    Ok(res)
+}
@@ -17,21 +17,31 @@
 
 use crate::{
     consensus::{
-        ConsensusClient, ConsensusClientId, ConsensusStateId, StateCommitment, StateMachineHeight,
-        StateMachineId,
+        ConsensusClient, ConsensusClientId, ConsensusStateId, PendingConsensusUpdate,
+        ProofMetadata, StateCommitment, StateMachineHeight, StateMachineId, BEEFY_CLIENT_ID,
+        GRANDPA_CLIENT_ID, SYNC_COMMITTEE_CLIENT_ID, TENDERMINT_CLIENT_ID,
     },
     error::Error,
+    events::{DeliveryOutcome, StateMachineUnfrozen, TraceEvent},
+    messaging::ConsensusAckMessage,
+    module::ModuleId,
     prelude::Vec,
     router::{IsmpRouter, Request},
     util::Keccak256,
 };
 use alloc::{
     boxed::Box,
+    collections::BTreeSet,
     format,
     string::{String, ToString},
 };
 use codec::{Decode, Encode};
-use core::{str::FromStr, time::Duration};
+use core::{
+    fmt::{self, Display, Formatter},
+    ops::RangeInclusive,
+    str::FromStr,
+    time::Duration,
+};
 use primitive_types::H256;
 
 /// Defines the necessary interfaces that must be satisfied by a state machine for it be ISMP
@@ -40,7 +50,9 @@ pub trait IsmpHost: Keccak256 {
     /// Should return the state machine type for the host.
     fn host_state_machine(&self) -> StateMachine;
 
-    /// Should return the latest height of the state machine
+    /// Should return the latest height of the state machine. Must return
+    /// [`Error::NoCommitmentHeight`], rather than a default height, if no commitment has been
+    /// stored for this state machine.
     fn latest_commitment_height(&self, id: StateMachineId) -> Result<u64, Error>;
 
     /// Should return the state machine at the given height
@@ -93,6 +105,28 @@ pub trait IsmpHost: Keccak256 {
     /// Should return Some(()) if a response has been received for the given request
     fn response_receipt(&self, res: &Request) -> Option<()>;
 
+    /// Should return the nonces of the requests from `source` to `dest` that have a request
+    /// receipt, as sorted ranges of consecutive nonces (see [`crate::util::compact_nonces`]), so
+    /// that relayers can monitor for missed deliveries. Nonces are allocated by the source across
+    /// all of its destinations, so a gap is only a missed delivery if the source dispatched that
+    /// nonce to `dest`. The default implementation reports no delivered nonces.
+    fn delivered_nonces(
+        &self,
+        _source: StateMachine,
+        _dest: StateMachine,
+    ) -> Vec<RangeInclusive<u64>> {
+        Vec::new()
+    }
+
+    /// Should return where the request with the given commitment is in its lifecycle, consulting
+    /// the request commitments, request and response receipts and timed-out requests. Hosts must
+    /// keep a record of timed-out requests to report [`RequestStatus::TimedOut`], since their
+    /// commitments are deleted. The default implementation reports every request as
+    /// [`RequestStatus::Unknown`].
+    fn request_status(&self, _commitment: H256) -> RequestStatus {
+        RequestStatus::Unknown
+    }
+
     /// Store a map of consensus_state_id to the consensus_client_id
     /// Should return an error if the consensus_state_id already exists
     fn store_consensus_state_id(
@@ -115,6 +149,25 @@ pub trait IsmpHost: Keccak256 {
         period: u64,
     ) -> Result<(), Error>;
 
+    /// Schedule an unbonding period to take effect from the next update of a consensus state,
+    /// passing `None` clears any scheduled period. The default implementation returns an error,
+    /// for hosts that don't allow the unbonding period to change.
+    fn store_pending_unbonding_period(
+        &self,
+        _consensus_state_id: ConsensusStateId,
+        _period: Option<u64>,
+    ) -> Result<(), Error> {
+        Err(Error::ImplementationSpecific(
+            "Changing unbonding periods is not supported".to_string(),
+        ))
+    }
+
+    /// Return the unbonding period scheduled to take effect from the next update of a consensus
+    /// state, if any. The default implementation never has one scheduled.
+    fn pending_unbonding_period(&self, _consensus_state_id: ConsensusStateId) -> Option<u64> {
+        None
+    }
+
     /// Store the timestamp when the consensus client was updated
     fn store_consensus_update_time(
         &self,
@@ -129,6 +182,15 @@ pub trait IsmpHost: Keccak256 {
         timestamp: Duration,
     ) -> Result<(), Error>;
 
+    /// Remove and return the state machine heights whose challenge period has elapsed at `now`,
+    /// i.e. the heights that just became usable for proofs. This is a drain, every height is
+    /// returned by exactly one call and never again. Hosts should call this once per block, so
+    /// that they can signal when a commitment is safe to prove against rather than only when it
+    /// was stored. The default implementation tracks no heights and returns none.
+    fn drain_elapsed_commitments(&self, _now: Duration) -> Vec<StateMachineHeight> {
+        Vec::new()
+    }
+
     /// Store the timestamp when the state machine was updated
     fn store_state_machine_commitment(
         &self,
@@ -136,15 +198,76 @@ pub trait IsmpHost: Keccak256 {
         state: StateCommitment,
     ) -> Result<(), Error>;
 
-    /// Freeze a state machine at the given height
-    fn freeze_state_machine(&self, height: StateMachineHeight) -> Result<(), Error>;
+    /// Store a batch of state machine commitments. Hosts may override this with an optimized bulk
+    /// write, the default implementation stores each commitment individually.
+    fn store_state_machine_commitments_batch(
+        &self,
+        commitments: Vec<(StateMachineHeight, StateCommitment)>,
+    ) -> Result<(), Error> {
+        for (height, commitment) in commitments {
+            self.store_state_machine_commitment(height, commitment)?;
+        }
+        Ok(())
+    }
+
+    /// Freeze a state machine at the given height. The reason should be stored alongside the
+    /// frozen height and reported in [`Error::FrozenStateMachine`].
+    fn freeze_state_machine(
+        &self,
+        height: StateMachineHeight,
+        reason: FreezeReason,
+    ) -> Result<(), Error>;
+
+    /// Unfreeze a state machine that was previously frozen at the given height. Should return
+    /// Ok(()) if the state machine is not frozen. The default implementation returns an error,
+    /// for hosts without governance.
+    fn unfreeze_state_machine(&self, _height: StateMachineHeight) -> Result<(), Error> {
+        Err(Error::ImplementationSpecific("Unfreezing state machines is not supported".to_string()))
+    }
+
+    /// Should return true if the given authenticated account is authorized to perform governance
+    /// actions such as unfreezing a state machine. The default implementation authorizes no one.
+    fn is_governance_authority(&self, _account: &[u8]) -> bool {
+        false
+    }
+
+    /// Should return true if the given authenticated account belongs to a peer host whose
+    /// acknowledgements of finalized heights may be recorded. The default implementation trusts no
+    /// one, for deployments without peers.
+    fn is_consensus_peer(&self, _account: &[u8]) -> bool {
+        false
+    }
 
     /// Freeze a consensus state with the given identifier
     fn freeze_consensus_client(&self, consensus_state_id: ConsensusStateId) -> Result<(), Error>;
 
+    /// Store the evidence of byzantine behaviour that caused a consensus state to be frozen. The
+    /// default implementation discards it.
+    fn store_byzantine_evidence(
+        &self,
+        _consensus_state_id: ConsensusStateId,
+        _evidence: Vec<u8>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Store latest height for a state machine
     fn store_latest_commitment_height(&self, height: StateMachineHeight) -> Result<(), Error>;
 
+    /// Delete the state commitments of the state machine above the given height that were updated
+    /// after `finalized_before`, i.e. that are still within their challenge period. Called when a
+    /// consensus client rolls back to another fork, commitments whose challenge period has elapsed
+    /// are final and must be kept. The default implementation returns an error, so that hosts
+    /// without support reject rollbacks rather than keep commitments from an abandoned fork.
+    fn invalidate_commitments_above(
+        &self,
+        _id: StateMachineId,
+        _height: u64,
+        _finalized_before: Duration,
+    ) -> Result<(), Error> {
+        Err(Error::ImplementationSpecific("Invalidating commitments is not supported".to_string()))
+    }
+
     /// Delete a request commitment from storage, used when a request is timed out
     fn delete_request_commitment(&self, req: &Request) -> Result<(), Error>;
 
@@ -158,6 +281,28 @@ pub trait IsmpHost: Keccak256 {
     /// Should return a handle to the consensus client based on the id
     fn consensus_client(&self, id: ConsensusClientId) -> Result<Box<dyn ConsensusClient>, Error>;
 
+    /// Return the ids of every consensus client implementation registered on the host. The default
+    /// implementation returns none, leaving [`IsmpHost::validate_client_registry`] nothing to
+    /// check.
+    fn consensus_clients(&self) -> Vec<ConsensusClientId> {
+        Vec::new()
+    }
+
+    /// Checks that every registered consensus client id is registered once and resolves to an
+    /// implementation identifying itself by the same id, returning
+    /// [`Error::AmbiguousConsensusClient`] otherwise. Hosts can call this at genesis to catch a
+    /// misconfigured registry before processing any messages.
+    fn validate_client_registry(&self) -> Result<(), Error> {
+        let mut seen = BTreeSet::new();
+        for id in self.consensus_clients() {
+            if !seen.insert(id) || self.consensus_client(id)?.consensus_client_id() != id {
+                Err(Error::AmbiguousConsensusClient { id })?
+            }
+        }
+
+        Ok(())
+    }
+
     /// Should return the configured delay period for a consensus state
     fn challenge_period(&self, consensus_state_id: ConsensusStateId) -> Option<Duration>;
 
@@ -182,12 +327,139 @@ pub trait IsmpHost: Keccak256 {
         Ok(())
     }
 
+    /// Should return true if consensus clients of this type may be created on the host.
+    /// Permissioned hosts can override this to restrict which consensus client implementations can
+    /// be instantiated.
+    fn is_client_type_allowed(&self, _id: ConsensusClientId) -> bool {
+        true
+    }
+
+    /// Should return true if consensus clients of this type may track the given state machine.
+    /// Defaults to the state machine's conventional client where it has one, see
+    /// [`StateMachine::default_consensus_client`]. Hosts can override this to relax or tighten the
+    /// check.
+    fn is_expected_client_type(&self, state_machine: StateMachine, id: ConsensusClientId) -> bool {
+        state_machine.default_consensus_client().is_none_or(|expected| expected == id)
+    }
+
     /// return the state machines that are allowed to proxy requests.
     fn allowed_proxies(&self) -> Vec<StateMachine>;
 
     /// Store the whitelist of allowed proxies, this should overwrite the existing whitelist.
     fn store_allowed_proxies(&self, allowed: Vec<StateMachine>);
 
+    /// Return the state machines this host can exchange requests with, this is the host's own
+    /// state machine and every state machine tracked by a registered consensus client.
+    fn supported_state_machines(&self) -> Vec<StateMachine>;
+
+    /// Checks if the host can exchange requests with this state machine.
+    fn is_supported_state_machine(&self, state_machine: &StateMachine) -> bool {
+        self.supported_state_machines().iter().any(|supported| supported == state_machine)
+    }
+
+    /// Checks if a module with this id is registered on the host. Hosts with a module registry
+    /// should override this so that requests to unknown modules are rejected before delivery.
+    fn is_registered_module(&self, _id: &ModuleId) -> bool {
+        true
+    }
+
+    /// Should return true if POST responses must come with a proof that their source received the
+    /// requests being responded to, see
+    /// [`crate::consensus::StateMachineClient::verify_response_binding`].
+    fn requires_response_binding(&self) -> bool {
+        false
+    }
+
+    /// Should return true if outgoing requests may be sent with an empty `from` module id.
+    fn allows_anonymous_dispatch(&self) -> bool {
+        false
+    }
+
+    /// Record that a membership proof with the given hash has been successfully verified. Hosts
+    /// may use this to skip redundant re-verification of identical proofs. The cache must be
+    /// size-bounded, the default implementation caches nothing.
+    fn cache_verified_proof(&self, _proof_hash: H256) {}
+
+    /// Returns true if a membership proof with the given hash was recently verified. Must only
+    /// return true for hashes previously passed to [`IsmpHost::cache_verified_proof`].
+    fn is_proof_verified(&self, _proof_hash: H256) -> bool {
+        false
+    }
+
+    /// Record the hash of the last consensus proof successfully applied to a consensus state.
+    /// Hosts may use this to reject retried submissions of the same proof before verifying it,
+    /// the default implementation records nothing.
+    fn store_last_consensus_proof(&self, _consensus_state_id: ConsensusStateId, _hash: H256) {}
+
+    /// Returns the hash of the last consensus proof successfully applied to a consensus state, if
+    /// it was recorded.
+    fn last_consensus_proof(&self, _consensus_state_id: ConsensusStateId) -> Option<H256> {
+        None
+    }
+
+    /// Whether consensus updates are applied optimistically. When enabled a verified update is
+    /// stored as pending and only promoted to the active consensus state once its own challenge
+    /// period has elapsed, so fraud proofs can be submitted against it in the meantime. Hosts
+    /// enabling this must store pending updates, see
+    /// [`IsmpHost::store_pending_consensus_update`]. Disabled by default.
+    fn optimistic_consensus_updates(&self) -> bool {
+        false
+    }
+
+    /// Store the consensus update awaiting promotion for a consensus state, passing `None` clears
+    /// it. Only called when [`IsmpHost::optimistic_consensus_updates`] is enabled, the default
+    /// implementation returns an error.
+    fn store_pending_consensus_update(
+        &self,
+        _consensus_state_id: ConsensusStateId,
+        _update: Option<PendingConsensusUpdate>,
+    ) -> Result<(), Error> {
+        Err(Error::ImplementationSpecific(
+            "Optimistic consensus updates are not supported".to_string(),
+        ))
+    }
+
+    /// Return the consensus update awaiting promotion for a consensus state, if any
+    fn pending_consensus_update(
+        &self,
+        _consensus_state_id: ConsensusStateId,
+    ) -> Option<PendingConsensusUpdate> {
+        None
+    }
+
+    /// The maximum number of keys a single GET request may read, `None` means unbounded. The
+    /// default implementation is unbounded.
+    fn max_get_keys(&self) -> Option<usize> {
+        None
+    }
+
+    /// Record a trace of a request reaching a stage in its lifecycle, for observability. The
+    /// default implementation does nothing.
+    fn trace(&self, _event: TraceEvent) {}
+
+    /// Record the outcome of delivering the request with the given commitment to its destination
+    /// module. Unlike request receipts, which prevent duplicate deliveries, this is an audit log of
+    /// every dispatched request. The default implementation does nothing.
+    fn record_delivery(&self, _commitment: H256, _outcome: DeliveryOutcome) {}
+
+    /// Record how the state proof for the GET request with the given commitment was verified, so
+    /// that suspiciously shallow proofs can be spotted. The default implementation does nothing.
+    fn record_proof_metadata(&self, _commitment: H256, _metadata: ProofMetadata) {}
+
+    /// Emit an event for a state machine that was unfrozen by governance. The default
+    /// implementation does nothing.
+    fn emit_state_machine_unfrozen(&self, _event: StateMachineUnfrozen) {}
+
+    /// Send an acknowledgement of a height that cleared its challenge period to peer hosts. The
+    /// default implementation does nothing, for deployments without peers.
+    fn emit_consensus_ack(&self, _ack: ConsensusAckMessage) {}
+
+    /// Record an acknowledgement received from a peer host. The default implementation does
+    /// nothing.
+    fn store_consensus_ack(&self, _ack: ConsensusAckMessage) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Checks if the host allows this state machine to proxy requests.
     fn is_allowed_proxy(&self, source: &StateMachine) -> bool {
         self.allowed_proxies().iter().any(|proxy| proxy == source)
@@ -199,6 +471,52 @@ pub trait IsmpHost: Keccak256 {
 
     /// Return a handle to the router
     fn ismp_router(&self) -> Box<dyn IsmpRouter>;
+
+    /// Return how incoming requests from state machines the host does not support should be
+    /// handled. Defaults to [`UnknownPolicy::Reject`].
+    fn on_unknown_state_machine(&self) -> UnknownPolicy {
+        UnknownPolicy::Reject
+    }
+}
+
+/// How a host handles incoming requests from a state machine it does not support. Messages
+/// referencing a [`StateMachine`] variant the host can't decode at all still fail to decode as a
+/// whole, this only applies to state machines that decode but aren't supported.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnknownPolicy {
+    /// Fail the whole message with [`Error::UnsupportedStateMachine`]
+    #[default]
+    Reject,
+    /// Drop the requests and continue without dispatching them
+    Ignore,
+}
+
+/// Why a state machine was frozen, reported to operators in [`Error::FrozenStateMachine`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode, scale_info::TypeInfo)]
+pub enum FreezeReason {
+    /// Conflicting commitments were observed for the same height
+    Equivocation,
+    /// A fraud proof against a commitment was verified
+    FraudProof,
+    /// Frozen by governance
+    Manual,
+    /// The consensus client can no longer verify the state machine
+    Expired,
+}
+
+/// Where a request is in its lifecycle, as reported by [`IsmpHost::request_status`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Encode, Decode, scale_info::TypeInfo)]
+pub enum RequestStatus {
+    /// The host has no record of the request
+    Unknown,
+    /// The request was dispatched from this host and awaits delivery
+    Committed,
+    /// The request was received on this host and delivered to its module
+    Delivered,
+    /// A response to the request dispatched from this host was received
+    Responded,
+    /// The request dispatched from this host timed out and its commitment was deleted
+    TimedOut,
 }
 
 /// Currently supported ethereum state machines.
@@ -208,16 +526,24 @@ pub trait IsmpHost: Keccak256 {
 #[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
 pub enum Ethereum {
     /// Ethereum Execution layer
+    #[codec(index = 0)]
     ExecutionLayer,
     /// The optimism state machine
+    #[codec(index = 1)]
     Optimism,
     /// The Arbitrum state machine
+    #[codec(index = 2)]
     Arbitrum,
     /// The Base state machine
+    #[codec(index = 3)]
     Base,
 }
 
 /// Currently supported state machines.
+///
+/// The scale encoding of this type is stored by hosts and carried in messages, new variants must be
+/// given a new codec index rather than reusing or shifting existing ones. Request commitments
+/// instead commit to the [`Display`] string of the state machine, which must remain stable too.
 #[derive(
     Clone, Debug, Copy, Encode, Decode, PartialOrd, Ord, PartialEq, Eq, Hash, scale_info::TypeInfo,
 )]
@@ -238,21 +564,121 @@ pub enum StateMachine {
     /// State machines chains running on beefy consensus state
     #[codec(index = 4)]
     Beefy(ConsensusStateId),
+    /// Cosmos chains running on Tendermint consensus, identified by their chain id
+    #[codec(index = 5)]
+    Tendermint(ChainId),
+}
+
+/// The maximum length in bytes of a [`ChainId`]
+pub const MAX_CHAIN_ID_LEN: usize = 32;
+
+/// A Tendermint chain identifier. It is stored inline as a bounded string, so that
+/// [`StateMachine`] remains `Copy`, and may only contain printable ASCII characters.
+#[derive(
+    Clone, Copy, Debug, Encode, PartialOrd, Ord, PartialEq, Eq, Hash, scale_info::TypeInfo,
+)]
+#[cfg_attr(
+    feature = "std",
+    derive(serde::Deserialize, serde::Serialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct ChainId {
+    len: u8,
+    bytes: [u8; MAX_CHAIN_ID_LEN],
+}
+
+impl ChainId {
+    /// Returns the chain id as a string slice
+    pub fn as_str(&self) -> &str {
+        // The contents are validated to be ASCII on construction
+        core::str::from_utf8(&self.bytes[..self.len as usize]).unwrap_or_default()
+    }
+}
+
+impl TryFrom<&str> for ChainId {
+    type Error = String;
+
+    fn try_from(id: &str) -> Result<Self, Self::Error> {
+        if id.is_empty() || id.len() > MAX_CHAIN_ID_LEN {
+            Err(format!("chain id must be between 1 and {MAX_CHAIN_ID_LEN} bytes: {id}"))?
+        }
+        if !id.bytes().all(|byte| byte.is_ascii_graphic()) {
+            Err(format!("chain id must be printable ASCII: {id}"))?
+        }
+
+        let mut bytes = [0u8; MAX_CHAIN_ID_LEN];
+        bytes[..id.len()].copy_from_slice(id.as_bytes());
+        Ok(Self { len: id.len() as u8, bytes })
+    }
+}
+
+impl TryFrom<String> for ChainId {
+    type Error = String;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        ChainId::try_from(id.as_str())
+    }
+}
+
+impl From<ChainId> for String {
+    fn from(id: ChainId) -> Self {
+        id.as_str().to_string()
+    }
+}
+
+impl Decode for ChainId {
+    fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let len = u8::decode(input)?;
+        let bytes = <[u8; MAX_CHAIN_ID_LEN]>::decode(input)?;
+        let id = bytes
+            .get(..len as usize)
+            .and_then(|id| core::str::from_utf8(id).ok())
+            .ok_or_else(|| codec::Error::from("invalid chain id"))?;
+        let chain_id = ChainId::try_from(id).map_err(|_| codec::Error::from("invalid chain id"))?;
+        // Reject non-canonical padding
+        if chain_id.bytes != bytes {
+            Err(codec::Error::from("invalid chain id"))?
+        }
+
+        Ok(chain_id)
+    }
+}
+
+impl Display for ChainId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
-impl ToString for StateMachine {
-    fn to_string(&self) -> String {
+impl StateMachine {
+    /// Returns the identifier of the consensus client conventionally used to track this state
+    /// machine, if there is one. Returns `None` where several client types are in common use, e.g.
+    /// parachains may be tracked by the parachain or BEEFY clients and L2s by their own clients.
+    pub fn default_consensus_client(&self) -> Option<ConsensusClientId> {
+        match self {
+            StateMachine::Ethereum(Ethereum::ExecutionLayer) => Some(SYNC_COMMITTEE_CLIENT_ID),
+            StateMachine::Grandpa(_) => Some(GRANDPA_CLIENT_ID),
+            StateMachine::Beefy(_) => Some(BEEFY_CLIENT_ID),
+            StateMachine::Tendermint(_) => Some(TENDERMINT_CLIENT_ID),
+            StateMachine::Ethereum(_) | StateMachine::Polkadot(_) | StateMachine::Kusama(_) => None,
+        }
+    }
+}
+
+impl Display for StateMachine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             StateMachine::Ethereum(ethereum) => match ethereum {
-                Ethereum::ExecutionLayer => "ETHE".to_string(),
-                Ethereum::Arbitrum => "ARBI".to_string(),
-                Ethereum::Optimism => "OPTI".to_string(),
-                Ethereum::Base => "BASE".to_string(),
+                Ethereum::ExecutionLayer => write!(f, "ETHE"),
+                Ethereum::Arbitrum => write!(f, "ARBI"),
+                Ethereum::Optimism => write!(f, "OPTI"),
+                Ethereum::Base => write!(f, "BASE"),
             },
-            StateMachine::Polkadot(id) => format!("POLKADOT-{id}"),
-            StateMachine::Kusama(id) => format!("KUSAMA-{id}"),
-            StateMachine::Grandpa(id) => format!("GRANDPA-{}", u32::from_be_bytes(*id)),
-            StateMachine::Beefy(id) => format!("BEEFY-{}", u32::from_be_bytes(*id)),
+            StateMachine::Polkadot(id) => write!(f, "POLKADOT-{id}"),
+            StateMachine::Kusama(id) => write!(f, "KUSAMA-{id}"),
+            StateMachine::Grandpa(id) => write!(f, "GRANDPA-{}", u32::from_be_bytes(*id)),
+            StateMachine::Beefy(id) => write!(f, "BEEFY-{}", u32::from_be_bytes(*id)),
+            StateMachine::Tendermint(id) => write!(f, "TENDERMINT-{id}"),
         }
     }
 }
@@ -269,7 +695,7 @@ impl FromStr for StateMachine {
             name if name.starts_with("POLKADOT-") => {
                 let id = name
                     .split('-')
-                    .last()
+                    .next_back()
                     .and_then(|id| u32::from_str(id).ok())
                     .ok_or_else(|| format!("invalid state machine: {name}"))?;
                 StateMachine::Polkadot(id)
@@ -277,7 +703,7 @@ impl FromStr for StateMachine {
             name if name.starts_with("KUSAMA-") => {
                 let id = name
                     .split('-')
-                    .last()
+                    .next_back()
                     .and_then(|id| u32::from_str(id).ok())
                     .ok_or_else(|| format!("invalid state machine: {name}"))?;
                 StateMachine::Kusama(id)
@@ -285,7 +711,7 @@ impl FromStr for StateMachine {
             name if name.starts_with("GRANDPA-") => {
                 let id = name
                     .split('-')
-                    .last()
+                    .next_back()
                     .and_then(|id| u32::from_str(id).ok().map(u32::to_be_bytes))
                     .ok_or_else(|| format!("invalid state machine: {name}"))?;
                 StateMachine::Grandpa(id)
@@ -293,11 +719,17 @@ impl FromStr for StateMachine {
             name if name.starts_with("BEEFY-") => {
                 let id = name
                     .split('-')
-                    .last()
+                    .next_back()
                     .and_then(|id| u32::from_str(id).ok().map(u32::to_be_bytes))
                     .ok_or_else(|| format!("invalid state machine: {name}"))?;
                 StateMachine::Beefy(id)
             }
+            name if name.starts_with("TENDERMINT-") => {
+                // Chain ids may themselves contain dashes, so only the prefix is stripped
+                let id = ChainId::try_from(&name["TENDERMINT-".len()..])
+                    .map_err(|_| format!("invalid state machine: {name}"))?;
+                StateMachine::Tendermint(id)
+            }
             name => Err(format!("Unknown state machine: {name}"))?,
         };
 
@@ -305,12 +737,56 @@ impl FromStr for StateMachine {
     }
 }
 
+impl TryFrom<&[u8]> for StateMachine {
+    type Error = Error;
+
+    /// Parses a state machine from the bytes of its [`Display`] string, as committed to in request
+    /// commitments. Non-canonical encodings are rejected.
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let unknown = || Error::UnknownStateMachine { bytes: bytes.to_vec() };
+        let name = core::str::from_utf8(bytes).map_err(|_| unknown())?;
+        let state_machine = StateMachine::from_str(name).map_err(|_| unknown())?;
+        if state_machine.to_string() != name {
+            Err(unknown())?
+        }
+
+        Ok(state_machine)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::host::{Ethereum, StateMachine};
-    use alloc::string::ToString;
+    use crate::{
+        consensus::{SYNC_COMMITTEE_CLIENT_ID, TENDERMINT_CLIENT_ID},
+        error::Error,
+        host::{ChainId, Ethereum, StateMachine, MAX_CHAIN_ID_LEN},
+    };
+    use alloc::{string::ToString, vec};
+    use codec::{Decode, Encode};
     use core::str::FromStr;
 
+    #[test]
+    fn state_machine_codec_indices_are_stable() {
+        let cases = [
+            (StateMachine::Ethereum(Ethereum::ExecutionLayer), vec![0, 0]),
+            (StateMachine::Ethereum(Ethereum::Optimism), vec![0, 1]),
+            (StateMachine::Ethereum(Ethereum::Arbitrum), vec![0, 2]),
+            (StateMachine::Ethereum(Ethereum::Base), vec![0, 3]),
+            (StateMachine::Polkadot(1000), vec![1, 232, 3, 0, 0]),
+            (StateMachine::Kusama(2000), vec![2, 208, 7, 0, 0]),
+            (StateMachine::Grandpa(*b"hybr"), vec![3, b'h', b'y', b'b', b'r']),
+            (StateMachine::Beefy(*b"hybr"), vec![4, b'h', b'y', b'b', b'r']),
+            (
+                StateMachine::Tendermint(ChainId::try_from("a").unwrap()),
+                [vec![5, 1, b'a'], vec![0u8; MAX_CHAIN_ID_LEN - 1]].concat(),
+            ),
+        ];
+
+        for (state_machine, encoding) in cases {
+            assert_eq!(state_machine.encode(), encoding, "{state_machine:?}");
+        }
+    }
+
     #[test]
     fn state_machine_conversions() {
         let grandpa = StateMachine::Grandpa(*b"hybr");
@@ -337,4 +813,52 @@ mod tests {
         assert_eq!(op, StateMachine::from_str(&op_str).unwrap());
         assert_eq!(base, StateMachine::from_str(&base_str).unwrap());
     }
+
+    #[test]
+    fn state_machine_default_consensus_clients() {
+        let tendermint = StateMachine::Tendermint(ChainId::try_from("osmosis-1").unwrap());
+        assert_eq!(
+            StateMachine::Ethereum(Ethereum::ExecutionLayer).default_consensus_client(),
+            Some(SYNC_COMMITTEE_CLIENT_ID)
+        );
+        assert_eq!(StateMachine::Ethereum(Ethereum::Base).default_consensus_client(), None);
+        assert_eq!(StateMachine::Kusama(2000).default_consensus_client(), None);
+        assert_eq!(tendermint.default_consensus_client(), Some(TENDERMINT_CLIENT_ID));
+    }
+
+    #[test]
+    fn state_machine_from_canonical_bytes() {
+        let cases = [
+            StateMachine::Ethereum(Ethereum::ExecutionLayer),
+            StateMachine::Polkadot(1000),
+            StateMachine::Kusama(2000),
+            StateMachine::Grandpa(*b"hybr"),
+            StateMachine::Beefy(*b"hybr"),
+            StateMachine::Tendermint(ChainId::try_from("osmosis-1").unwrap()),
+        ];
+        for state_machine in cases {
+            let bytes = state_machine.to_string().into_bytes();
+            assert_eq!(StateMachine::try_from(&bytes[..]).unwrap(), state_machine);
+        }
+
+        for bytes in [&b"SOLANA"[..], b"POLKADOT-01", b"POLKADOT-", &[0xff, 0xfe]] {
+            assert!(matches!(
+                StateMachine::try_from(bytes),
+                Err(Error::UnknownStateMachine { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn chain_ids_are_bounded() {
+        let longest = "a".repeat(MAX_CHAIN_ID_LEN);
+        assert_eq!(ChainId::try_from(longest.as_str()).unwrap().as_str(), longest);
+        assert!(ChainId::try_from("a".repeat(MAX_CHAIN_ID_LEN + 1).as_str()).is_err());
+        assert!(ChainId::try_from("").is_err());
+        assert!(ChainId::try_from("osmosis 1").is_err());
+
+        let mut encoded = ChainId::try_from("a").unwrap().encode();
+        encoded[0] = MAX_CHAIN_ID_LEN as u8 + 1;
+        assert!(ChainId::decode(&mut &encoded[..]).is_err());
+    }
 }
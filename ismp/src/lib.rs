@@ -29,14 +29,31 @@ extern crate core;
 pub mod consensus;
 pub mod error;
 pub mod events;
+#[cfg(feature = "evm-proof")]
+pub mod evm_proof;
 pub mod handlers;
 pub mod host;
 pub mod messaging;
 pub mod module;
 pub mod router;
+#[cfg(feature = "substrate-proof")]
+pub mod substrate_proof;
 pub mod util;
+pub mod verify;
+pub mod version;
 
 pub mod prelude {
-    //! Some useful imports in the crate prelude.
+    //! Some useful imports in the crate prelude, including the stable public API that
+    //! integrators are expected to use.
+    pub use crate::{
+        consensus::{ConsensusClient, StateMachineClient},
+        error::Error,
+        handlers::{handle_incoming_message, MessageResult},
+        host::{IsmpHost, StateMachine},
+        messaging::Message,
+        module::IsmpModule,
+        router::{IsmpDispatcher, IsmpRouter, Request, Response},
+        util::now_from_host,
+    };
     pub use alloc::{format, str::FromStr, string::String, vec, vec::Vec};
 }
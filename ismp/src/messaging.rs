@@ -24,9 +24,11 @@ use crate::{
     },
     error::Error,
     router::{Post, Request, Response},
+    version::ISMP_PROTOCOL_VERSION,
 };
-use alloc::{string::ToString, vec::Vec};
-use codec::{Decode, Encode};
+use alloc::{collections::BTreeSet, string::ToString, vec::Vec};
+use codec::{Decode, DecodeLimit as _, Encode};
+use primitive_types::H256;
 
 /// A consensus message is used to update the state of a consensus client and its children state
 /// machines.
@@ -36,6 +38,31 @@ pub struct ConsensusMessage {
     pub consensus_proof: Vec<u8>,
     /// The consensus state Id
     pub consensus_state_id: ConsensusStateId,
+    /// The keccak256 hash of the trusted consensus state this proof was built against. If
+    /// present, the update is rejected when the stored consensus state no longer matches.
+    pub expected_trusted_state: Option<H256>,
+    /// The protocol version the sender was built against, see [`crate::version`]. Messages
+    /// without a declared version are assumed to be compatible.
+    pub protocol_version: Option<u16>,
+}
+
+impl ConsensusMessage {
+    /// Construct a consensus message, returns an error if the consensus proof is empty.
+    pub fn new(
+        consensus_proof: Vec<u8>,
+        consensus_state_id: ConsensusStateId,
+    ) -> Result<Self, Error> {
+        if consensus_proof.is_empty() {
+            Err(Error::EmptyMessage)?
+        }
+
+        Ok(Self {
+            consensus_proof,
+            consensus_state_id,
+            expected_trusted_state: None,
+            protocol_version: Some(ISMP_PROTOCOL_VERSION),
+        })
+    }
 }
 
 /// A fraud proof message is used to report byzantine misbehaviour in a consensus system.
@@ -75,6 +102,99 @@ pub struct CreateConsensusState {
     pub state_machine_commitments: Vec<(StateMachineId, StateCommitmentHeight)>,
 }
 
+impl CreateConsensusState {
+    /// Construct a message to create a consensus state. Returns an error if the message fails
+    /// [`CreateConsensusState::validate`].
+    pub fn new(
+        consensus_state: Vec<u8>,
+        consensus_client_id: ConsensusClientId,
+        consensus_state_id: ConsensusStateId,
+        unbonding_period: u64,
+        challenge_period: u64,
+        state_machine_commitments: Vec<(StateMachineId, StateCommitmentHeight)>,
+    ) -> Result<Self, Error> {
+        let message = Self {
+            consensus_state,
+            consensus_client_id,
+            consensus_state_id,
+            unbonding_period,
+            challenge_period,
+            state_machine_commitments,
+        };
+        message.validate()?;
+        Ok(message)
+    }
+
+    /// Check the message is well formed. Returns an error if any commitment belongs to another
+    /// consensus state, is repeated, or has a zero height or state root. A message without
+    /// commitments is valid, clients may start from a consensus state alone and receive their
+    /// first commitments with the next update. The fields are public, so messages built or decoded
+    /// without [`CreateConsensusState::new`] are checked again when the client is created.
+    pub fn validate(&self) -> Result<(), Error> {
+        let mut heights = BTreeSet::new();
+        for (id, commitment) in &self.state_machine_commitments {
+            let height = StateMachineHeight { id: *id, height: commitment.height };
+            if id.consensus_state_id != self.consensus_state_id {
+                Err(Error::ConsensusStateIdMismatch {
+                    expected: self.consensus_state_id,
+                    got: id.consensus_state_id,
+                })?
+            }
+            if commitment.height == 0 {
+                Err(Error::ZeroCommitmentHeight { id: *id })?
+            }
+            if commitment.commitment.state_root.is_zero() {
+                Err(Error::ZeroStateRoot { height })?
+            }
+            if !heights.insert(height) {
+                Err(Error::DuplicateStateCommitment { height })?
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A governance message used to lift the freeze on a state machine after an incident has been
+/// resolved. It is not relayed like other messages, hosts submit it through
+/// [`unfreeze_state_machine`] along with the origin they authenticated.
+///
+/// [`unfreeze_state_machine`]: crate::handlers::unfreeze_state_machine
+#[derive(Debug, Clone, Encode, Decode, scale_info::TypeInfo, PartialEq, Eq)]
+pub struct UnfreezeStateMachineMessage {
+    /// The frozen state machine height
+    pub height: StateMachineHeight,
+}
+
+/// A governance message used to change the unbonding period of a consensus state. The new period
+/// only takes effect from the next consensus update, so that shortening it cannot instantly expire
+/// a client which is still within its current unbonding window. Like
+/// [`UnfreezeStateMachineMessage`], hosts submit it through [`set_unbonding_period`] along with the
+/// origin they authenticated.
+///
+/// [`set_unbonding_period`]: crate::handlers::set_unbonding_period
+#[derive(Debug, Clone, Encode, Decode, scale_info::TypeInfo, PartialEq, Eq)]
+pub struct SetUnbondingPeriodMessage {
+    /// The consensus state whose unbonding period should change
+    pub consensus_state_id: ConsensusStateId,
+    /// The new unbonding period in seconds
+    pub new_period: u64,
+}
+
+/// An acknowledgement gossiped between hosts that a state machine height has cleared its
+/// challenge period on the sending host. Acknowledgements carry no proof, so they are submitted by
+/// authenticated peers through [`crate::handlers::record_consensus_ack`] rather than as a
+/// [`Message`]. They are advisory and must never be used in place of verifying a proof.
+#[derive(Debug, Clone, Copy, Encode, Decode, scale_info::TypeInfo, PartialEq, Eq)]
+pub struct ConsensusAckMessage {
+    /// The consensus state the height was finalized through on the sending host
+    pub consensus_state_id: ConsensusStateId,
+    /// The state machine height that cleared its challenge period
+    pub height: StateMachineHeight,
+    /// The state root of the commitment at that height
+    pub commitment_root: H256,
+}
+
 /// A request message holds a batch of requests to be dispatched from a source state machine
 #[derive(Debug, Clone, Encode, Decode, scale_info::TypeInfo, PartialEq, Eq)]
 pub struct RequestMessage {
@@ -84,6 +204,17 @@ pub struct RequestMessage {
     pub proof: Proof,
 }
 
+impl RequestMessage {
+    /// Construct a request message, returns an error if there are no requests.
+    pub fn new(requests: Vec<Post>, proof: Proof) -> Result<Self, Error> {
+        if requests.is_empty() {
+            Err(Error::EmptyMessage)?
+        }
+
+        Ok(Self { requests, proof })
+    }
+}
+
 /// A request message holds a batch of responses to be dispatched from a source state machine
 #[derive(Debug, Clone, Encode, Decode, scale_info::TypeInfo, PartialEq, Eq)]
 pub enum ResponseMessage {
@@ -93,6 +224,10 @@ pub enum ResponseMessage {
         responses: Vec<Response>,
         /// Membership batch proof for these responses
         proof: Proof,
+        /// State proof of the receipts for the requests being responded to, at the same height as
+        /// the membership proof. Only required by hosts that check the binding, see
+        /// [`crate::host::IsmpHost::requires_response_binding`].
+        receipt_proof: Option<Vec<u8>>,
     },
     /// A GET request for querying data
     Get {
@@ -104,6 +239,26 @@ pub enum ResponseMessage {
 }
 
 impl ResponseMessage {
+    /// Construct a message for POST responses, returns an error if there are no responses.
+    pub fn new_post(responses: Vec<Response>, proof: Proof) -> Result<Self, Error> {
+        if responses.is_empty() {
+            Err(Error::EmptyMessage)?
+        }
+
+        Ok(ResponseMessage::Post { responses, proof, receipt_proof: None })
+    }
+
+    /// Construct a message for GET responses, returns an error if there are no requests or if the
+    /// proof height does not match the retrieval height of every request.
+    pub fn new_get(requests: Vec<Request>, proof: Proof) -> Result<Self, Error> {
+        if requests.is_empty() {
+            Err(Error::EmptyMessage)?
+        }
+        sufficient_proof_height(&requests, &proof)?;
+
+        Ok(ResponseMessage::Get { requests, proof })
+    }
+
     /// Returns the requests in this message.
     pub fn requests(&self) -> Vec<Request> {
         match self {
@@ -156,6 +311,24 @@ pub enum TimeoutMessage {
 }
 
 impl TimeoutMessage {
+    /// Construct a timeout message for POST requests, returns an error if there are no requests.
+    pub fn new_post(requests: Vec<Request>, timeout_proof: Proof) -> Result<Self, Error> {
+        if requests.is_empty() {
+            Err(Error::EmptyMessage)?
+        }
+
+        Ok(TimeoutMessage::Post { requests, timeout_proof })
+    }
+
+    /// Construct a timeout message for GET requests, returns an error if there are no requests.
+    pub fn new_get(requests: Vec<Request>) -> Result<Self, Error> {
+        if requests.is_empty() {
+            Err(Error::EmptyMessage)?
+        }
+
+        Ok(TimeoutMessage::Get { requests })
+    }
+
     /// Returns the requests in this message.
     pub fn requests(&self) -> &[Request] {
         match self {
@@ -204,3 +377,161 @@ pub enum Message {
     #[codec(index = 4)]
     Timeout(TimeoutMessage),
 }
+
+impl Message {
+    /// Returns the protocol version declared by this message, if any.
+    pub fn protocol_version(&self) -> Option<u16> {
+        match self {
+            Message::Consensus(msg) => msg.protocol_version,
+            _ => None,
+        }
+    }
+}
+
+/// Limits applied when decoding a [`Message`] from untrusted bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimit {
+    /// The maximum nesting depth of the decoded message
+    pub depth: u32,
+}
+
+impl Default for DecodeLimit {
+    fn default() -> Self {
+        Self { depth: 256 }
+    }
+}
+
+impl Message {
+    /// Decode a message from untrusted bytes, rejecting encodings nested deeper than the limit
+    /// so that malicious inputs cannot exhaust the stack.
+    pub fn decode_bounded(bytes: &[u8], limit: DecodeLimit) -> Result<Self, Error> {
+        Self::decode_with_depth_limit(limit.depth, &mut &bytes[..])
+            .map_err(|e| Error::MessageDecodeFailed(e.to_string()))
+    }
+
+    /// Encode the message for transmission over the network.
+    pub fn to_scale_bytes(&self) -> Vec<u8> {
+        self.encode()
+    }
+
+    /// Decode a message received over the network with the default [`DecodeLimit`]. Unlike
+    /// [`Message::decode_bounded`] the entire input must be consumed.
+    pub fn from_scale_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::decode_all_with_depth_limit(DecodeLimit::default().depth, &mut &bytes[..])
+            .map_err(|e| Error::MessageDecodeFailed(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        consensus::{StateCommitment, StateMachineHeight, StateMachineId},
+        error::Error,
+        host::{Ethereum, StateMachine},
+        messaging::{
+            ConsensusMessage, CreateConsensusState, DecodeLimit, Message, Proof, RequestMessage,
+            ResponseMessage, StateCommitmentHeight, TimeoutMessage,
+        },
+        router::{Get, Request},
+    };
+    use alloc::vec;
+    use codec::Encode;
+    use primitive_types::H256;
+
+    #[test]
+    fn message_constructors_enforce_invariants() {
+        let height = StateMachineHeight {
+            id: StateMachineId {
+                state_id: StateMachine::Ethereum(Ethereum::ExecutionLayer),
+                consensus_state_id: *b"mock",
+            },
+            height: 10,
+        };
+        let proof = Proof { height, proof: vec![] };
+        let get = |height| {
+            Request::Get(Get {
+                source: StateMachine::Polkadot(1000),
+                dest: StateMachine::Ethereum(Ethereum::ExecutionLayer),
+                nonce: 0,
+                from: vec![],
+                keys: vec![vec![0u8; 32]],
+                height,
+                timeout_timestamp: 0,
+                gas_limit: 0,
+            })
+        };
+
+        assert!(matches!(ConsensusMessage::new(vec![], *b"mock"), Err(Error::EmptyMessage)));
+        assert!(ConsensusMessage::new(vec![0u8], *b"mock").is_ok());
+        assert!(matches!(RequestMessage::new(vec![], proof.clone()), Err(Error::EmptyMessage)));
+        assert!(matches!(
+            ResponseMessage::new_post(vec![], proof.clone()),
+            Err(Error::EmptyMessage)
+        ));
+        assert!(matches!(TimeoutMessage::new_get(vec![]), Err(Error::EmptyMessage)));
+        assert!(matches!(
+            ResponseMessage::new_get(vec![get(9)], proof.clone()),
+            Err(Error::InsufficientProofHeight)
+        ));
+        assert!(ResponseMessage::new_get(vec![get(10)], proof).is_ok());
+    }
+
+    #[test]
+    fn create_consensus_state_validates_commitments() {
+        let id = StateMachineId {
+            state_id: StateMachine::Ethereum(Ethereum::ExecutionLayer),
+            consensus_state_id: *b"mock",
+        };
+        let commitment = |height, state_root| StateCommitmentHeight {
+            commitment: StateCommitment { timestamp: 1, overlay_root: None, state_root },
+            height,
+        };
+        let create = |commitments| {
+            CreateConsensusState::new(vec![], *b"MOCK", *b"mock", 1_000, 0, commitments)
+        };
+        let root = H256::repeat_byte(1);
+        let height = StateMachineHeight { id, height: 1 };
+
+        assert!(create(vec![]).is_ok());
+        assert!(matches!(
+            create(vec![(id, commitment(0, root))]),
+            Err(Error::ZeroCommitmentHeight { id: found }) if found == id
+        ));
+        assert!(matches!(
+            create(vec![(StateMachineId { consensus_state_id: *b"othr", ..id }, commitment(1, root))]),
+            Err(Error::ConsensusStateIdMismatch { expected, got })
+                if expected == *b"mock" && got == *b"othr"
+        ));
+        assert!(matches!(
+            create(vec![(id, commitment(1, H256::zero()))]),
+            Err(Error::ZeroStateRoot { height: found }) if found == height
+        ));
+        assert!(matches!(
+            create(vec![(id, commitment(1, root)), (id, commitment(1, root))]),
+            Err(Error::DuplicateStateCommitment { height: found }) if found == height
+        ));
+        assert!(create(vec![(id, commitment(1, root)), (id, commitment(2, root))]).is_ok());
+    }
+
+    #[test]
+    fn decode_bounded_enforces_depth_limit() {
+        let message = Message::Timeout(TimeoutMessage::Get { requests: vec![] });
+        let bytes = message.encode();
+
+        assert_eq!(Message::decode_bounded(&bytes, DecodeLimit::default()).unwrap(), message);
+        assert!(matches!(
+            Message::decode_bounded(&bytes, DecodeLimit { depth: 0 }),
+            Err(Error::MessageDecodeFailed(_))
+        ));
+    }
+
+    #[test]
+    fn scale_bytes_round_trip() {
+        let message = Message::Timeout(TimeoutMessage::Get { requests: vec![] });
+        let mut bytes = message.to_scale_bytes();
+
+        assert_eq!(Message::from_scale_bytes(&bytes).unwrap(), message);
+        bytes.push(0);
+        assert!(matches!(Message::from_scale_bytes(&bytes), Err(Error::MessageDecodeFailed(_))));
+    }
+}
@@ -18,12 +18,80 @@
 use crate::{
     error::Error,
     host::StateMachine,
-    router::{Post as PostRequest, Request, Response},
+    router::{IsmpRouter, Post as PostRequest, Request, Response},
 };
-use alloc::string::String;
+use alloc::{boxed::Box, collections::BTreeMap, rc::Rc, string::String, vec, vec::Vec};
+use codec::{Decode, Encode};
+use primitive_types::H256;
 
-/// The result of successfully dispatching a request or response
-#[derive(Debug, PartialEq, Eq)]
+/// The identifier of a module on a state machine
+#[derive(
+    Debug, Clone, Encode, Decode, scale_info::TypeInfo, PartialEq, Eq, Hash, Ord, PartialOrd,
+)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
+pub struct ModuleId(pub Vec<u8>);
+
+impl ModuleId {
+    /// Returns the identifier of a module addressed by a single byte pallet index
+    pub fn pallet(index: u8) -> Self {
+        Self(vec![index])
+    }
+
+    /// Returns the pallet index if this is a single byte identifier
+    pub fn as_pallet_index(&self) -> Option<u8> {
+        match self.0[..] {
+            [index] => Some(index),
+            _ => None,
+        }
+    }
+
+    /// Returns the identifier as a fixed 32 byte array if it has that length
+    pub fn as_fixed(&self) -> Option<[u8; 32]> {
+        self.0[..].try_into().ok()
+    }
+}
+
+impl AsRef<[u8]> for ModuleId {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for ModuleId {
+    fn from(id: Vec<u8>) -> Self {
+        Self(id)
+    }
+}
+
+impl From<&[u8]> for ModuleId {
+    fn from(id: &[u8]) -> Self {
+        Self(id.to_vec())
+    }
+}
+
+impl From<u8> for ModuleId {
+    fn from(index: u8) -> Self {
+        Self::pallet(index)
+    }
+}
+
+impl From<[u8; 32]> for ModuleId {
+    fn from(id: [u8; 32]) -> Self {
+        Self(id.to_vec())
+    }
+}
+
+impl From<H256> for ModuleId {
+    fn from(id: H256) -> Self {
+        Self(id.as_bytes().to_vec())
+    }
+}
+
+/// The result of successfully dispatching a request or response. The source and destination
+/// follow the direction of the message that was handled, see
+/// [`crate::handlers::MessageResult`].
+#[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
 pub struct DispatchSuccess {
     /// Destination chain for request or response
     pub dest_chain: StateMachine,
@@ -31,10 +99,18 @@ pub struct DispatchSuccess {
     pub source_chain: StateMachine,
     /// Request nonce
     pub nonce: u64,
+    /// True if a request was delivered after its soft delivery deadline, always false for
+    /// responses and timeouts.
+    pub late: bool,
+    /// Gas the module reported using while processing a timeout, always zero for requests and
+    /// responses.
+    pub gas_used: u64,
 }
 
-/// The result of unsuccessfully dispatching a request or response
-#[derive(Debug, PartialEq, Eq)]
+/// The result of unsuccessfully dispatching a request or response. The source and destination
+/// follow the same convention as [`DispatchSuccess`].
+#[derive(Debug, Clone, Encode, Decode, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
 pub struct DispatchError {
     /// Descriptive error message
     pub msg: String,
@@ -46,9 +122,54 @@ pub struct DispatchError {
     pub dest_chain: StateMachine,
 }
 
+/// The outcome of notifying a module of a timed-out request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutOutcome {
+    /// Gas used by the module while processing the timeout
+    pub gas_used: u64,
+    /// True if the module fully reverted the effects of the timed-out request. If false, the
+    /// request commitment is retained so that the timeout can be retried.
+    pub rolled_back: bool,
+}
+
 /// A type alias for dispatch results
 pub type DispatchResult = Result<DispatchSuccess, DispatchError>;
 
+/// Accessors for the fields shared by both variants of a [`DispatchResult`]
+pub trait DispatchResultExt {
+    /// Request nonce
+    fn nonce(&self) -> u64;
+
+    /// Source chain for request or response
+    fn source(&self) -> StateMachine;
+
+    /// Destination chain for request or response
+    fn dest(&self) -> StateMachine;
+}
+
+impl DispatchResultExt for DispatchResult {
+    fn nonce(&self) -> u64 {
+        match self {
+            Ok(success) => success.nonce,
+            Err(error) => error.nonce,
+        }
+    }
+
+    fn source(&self) -> StateMachine {
+        match self {
+            Ok(success) => success.source_chain,
+            Err(error) => error.source_chain,
+        }
+    }
+
+    fn dest(&self) -> StateMachine {
+        match self {
+            Ok(success) => success.dest_chain,
+            Err(error) => error.dest_chain,
+        }
+    }
+}
+
 /// Individual modules which live on a state machine must conform to this interface in order to send
 /// and receive ISMP requests and responses
 pub trait IsmpModule {
@@ -61,6 +182,77 @@ pub trait IsmpModule {
     fn on_response(&self, response: Response) -> Result<(), Error>;
 
     /// Called by the message handler on a module, to notify module of requests that were previously
-    /// sent but have now timed-out
-    fn on_timeout(&self, request: Request) -> Result<(), Error>;
+    /// sent but have now timed-out. The module reports whether it rolled back the effects of the
+    /// request through the returned [`TimeoutOutcome`]. If it doesn't roll back the request, or
+    /// returns [`Error::RetryTimeout`], the request commitment is retained so that the timeout can
+    /// be retried. Any other error is final and the commitment is deleted.
+    fn on_timeout(&self, request: Request) -> Result<TimeoutOutcome, Error>;
+
+    /// Returns true if `caller` may dispatch outgoing requests whose `from` field claims to be this
+    /// module. The caller is authenticated by the dispatcher. Permissioned modules should override
+    /// this to prevent other modules from spoofing their identity.
+    fn is_authorized_source(&self, _caller: &[u8], _from: &[u8]) -> bool {
+        true
+    }
+}
+
+impl<T: IsmpModule + ?Sized> IsmpModule for Rc<T> {
+    fn on_accept(&self, request: PostRequest) -> Result<(), Error> {
+        (**self).on_accept(request)
+    }
+
+    fn on_response(&self, response: Response) -> Result<(), Error> {
+        (**self).on_response(response)
+    }
+
+    fn on_timeout(&self, request: Request) -> Result<TimeoutOutcome, Error> {
+        (**self).on_timeout(request)
+    }
+
+    fn is_authorized_source(&self, caller: &[u8], from: &[u8]) -> bool {
+        (**self).is_authorized_source(caller, from)
+    }
+}
+
+/// A registry of modules addressed by their [`ModuleId`], whether that is a pallet index or a
+/// fixed 32 byte identifier. The registry can be used directly as a host's [`IsmpRouter`].
+#[derive(Default, Clone)]
+pub struct ModuleRegistry {
+    modules: BTreeMap<ModuleId, Rc<dyn IsmpModule>>,
+}
+
+impl ModuleRegistry {
+    /// Register a module under the given identifier. Returns [`Error::DuplicateModule`] if a
+    /// module is already registered under it.
+    pub fn register(
+        &mut self,
+        id: impl Into<ModuleId>,
+        module: Rc<dyn IsmpModule>,
+    ) -> Result<(), Error> {
+        let id = id.into();
+        if self.modules.contains_key(&id) {
+            return Err(Error::DuplicateModule { id })
+        }
+        self.modules.insert(id, module);
+
+        Ok(())
+    }
+
+    /// Returns true if a module is registered under the given identifier
+    pub fn contains(&self, id: &ModuleId) -> bool {
+        self.modules.contains_key(id)
+    }
+
+    /// Returns the module registered under the given identifier
+    pub fn resolve(&self, id: &ModuleId) -> Option<Rc<dyn IsmpModule>> {
+        self.modules.get(id).cloned()
+    }
+}
+
+impl IsmpRouter for ModuleRegistry {
+    fn module_for_id(&self, bytes: Vec<u8>) -> Result<Box<dyn IsmpModule>, Error> {
+        let id = ModuleId::from(bytes);
+        let module = self.resolve(&id).ok_or(Error::UnknownModule { id })?;
+        Ok(Box::new(module))
+    }
 }
@@ -15,11 +15,29 @@
 
 //! IsmpRouter definition
 
-use crate::{error::Error, host::StateMachine, module::IsmpModule, prelude::Vec};
-use alloc::{boxed::Box, collections::BTreeMap, string::ToString};
+use crate::{
+    consensus::StateProofResult,
+    error::Error,
+    host::{IsmpHost, StateMachine},
+    module::IsmpModule,
+    prelude::Vec,
+    util::{hash_request, Keccak256},
+};
+use alloc::{boxed::Box, string::ToString};
 use codec::{Decode, Encode};
 use core::time::Duration;
 
+/// The current version of the POST request format.
+///
+/// The version is the first byte of a POST request's commitment preimage, and must be incremented
+/// whenever fields are added to or removed from the commitment. Hosts reject requests with a
+/// version greater than the one they support, rather than computing a commitment for a request
+/// they cannot fully interpret.
+pub const REQUEST_VERSION: u8 = 1;
+
+/// The maximum size in bytes of the metadata attached to a POST request
+pub const MAX_POST_META_LEN: usize = 1024;
+
 /// The ISMP POST request.
 #[derive(Debug, Clone, Encode, Decode, PartialEq, Eq, scale_info::TypeInfo)]
 #[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
@@ -41,6 +59,33 @@ pub struct Post {
     /// Gas limit for executing the request on destination
     /// This value should be zero if destination module is not a contract
     pub gas_limit: u64,
+    /// Optional soft delivery deadline in seconds. Requests delivered after this deadline are
+    /// still accepted but are flagged as late, unlike `timeout_timestamp` which is a hard limit.
+    pub deliver_by: Option<u64>,
+    /// The version of the request format, see [`REQUEST_VERSION`]
+    pub version: u8,
+    /// Module ID that should receive the response to this request, if not the sending module.
+    pub response_to: Option<Vec<u8>>,
+    /// Opaque application metadata for middleware such as routers or fee managers, kept apart
+    /// from `data`. At most [`MAX_POST_META_LEN`] bytes.
+    pub meta: Option<Vec<u8>>,
+    /// Whether the destination module is expected to respond. Fire-and-forget requests are not
+    /// tracked for a response and any response to them is rejected.
+    pub expects_response: bool,
+}
+
+impl Post {
+    /// Returns true if the request is delivered after its soft delivery deadline, if any.
+    pub fn is_late(&self, proof_timestamp: Duration) -> bool {
+        self.deliver_by
+            .map(|deliver_by| proof_timestamp > Duration::from_secs(deliver_by))
+            .unwrap_or(false)
+    }
+
+    /// Construct the response to this request with the given response bytes
+    pub fn respond(self, response: Vec<u8>) -> PostResponse {
+        PostResponse { post: self, response }
+    }
 }
 
 /// The ISMP GET request.
@@ -67,7 +112,8 @@ pub struct Get {
     /// For fetching keys from EVM contracts each key should be 52 bytes
     /// This should be a concatenation of contract address and slot hash
     pub keys: Vec<Vec<u8>>,
-    /// Height at which to read the state machine.
+    /// Height at which to read the state machine. Responses are only accepted once the state
+    /// commitment at this height has passed its challenge period and is not frozen.
     pub height: u64,
     /// Host timestamp at which this request expires in seconds
     pub timeout_timestamp: u64,
@@ -76,6 +122,13 @@ pub struct Get {
     pub gas_limit: u64,
 }
 
+impl Get {
+    /// Construct the response to this request with the values read from the counterparty
+    pub fn respond(self, values: StateProofResult) -> GetResponse {
+        GetResponse { get: self, values }
+    }
+}
+
 /// The ISMP request.
 #[derive(Debug, Clone, Encode, Decode, PartialEq, Eq, scale_info::TypeInfo)]
 #[cfg_attr(feature = "std", derive(serde::Deserialize, serde::Serialize))]
@@ -89,6 +142,13 @@ pub enum Request {
 }
 
 impl Request {
+    /// Returns true if both requests have the same commitment, i.e. they agree on every field that
+    /// is committed to by [`hash_request`]. Unlike the derived [`PartialEq`], fields that are not
+    /// part of the commitment are ignored, so this is what duplicate detection should use.
+    pub fn commitment_eq<H: Keccak256>(&self, other: &Self) -> bool {
+        hash_request::<H>(self) == hash_request::<H>(other)
+    }
+
     /// Get the source chain
     pub fn source_chain(&self) -> StateMachine {
         match self {
@@ -145,7 +205,8 @@ impl Request {
         }
     }
 
-    /// Returns the timeout timestamp for a request
+    /// Returns the timeout timestamp for a request. A zero timeout means the request never times
+    /// out and is reported as `u64::MAX` seconds, the same as the largest timeout timestamp.
     pub fn timeout(&self) -> Duration {
         let timeout = match self {
             Request::Post(post) => post.timeout_timestamp,
@@ -160,9 +221,23 @@ impl Request {
         }
     }
 
-    /// Returns true if the destination chain timestamp has exceeded the request timeout timestamp
+    /// Returns true if the destination chain timestamp has exceeded the request timeout timestamp.
+    /// Requests with the largest timeout never time out, whatever timestamp a state machine
+    /// reports.
     pub fn timed_out(&self, proof_timestamp: Duration) -> bool {
-        proof_timestamp >= self.timeout()
+        let timeout = self.timeout();
+        timeout.as_secs() != u64::MAX && proof_timestamp >= timeout
+    }
+
+    /// Construct the response to a POST request with the given response bytes. GET requests are
+    /// answered with state proof values instead, see [`Get::respond`].
+    pub fn respond(self, response: Vec<u8>) -> Result<Response, Error> {
+        match self {
+            Request::Post(post) => Ok(Response::Post(post.respond(response))),
+            Request::Get(_) => Err(Error::ImplementationSpecific(
+                "Get requests must be responded to with Get::respond".to_string(),
+            )),
+        }
     }
 
     /// Returns a get request or an error
@@ -200,8 +275,9 @@ pub struct PostResponse {
 pub struct GetResponse {
     /// The Get request that triggered this response.
     pub get: Get,
-    /// Values derived from the state proof
-    pub values: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+    /// Values derived from the state proof, a `None` value is a verified proof that the key is
+    /// absent from the counterparty's state.
+    pub values: StateProofResult,
 }
 
 /// The ISMP response
@@ -227,7 +303,9 @@ impl Response {
     pub fn destination_module(&self) -> Vec<u8> {
         match self {
             Response::Get(get) => get.get.from.clone(),
-            Response::Post(post) => post.post.from.clone(),
+            Response::Post(post) => {
+                post.post.response_to.clone().unwrap_or_else(|| post.post.from.clone())
+            }
         }
     }
 
@@ -264,6 +342,16 @@ pub enum RequestResponse {
     Response(Vec<Response>),
 }
 
+/// Borrowed equivalent of [`RequestResponse`], used to verify membership without cloning the
+/// batch.
+#[derive(Debug, Clone, Copy, Encode)]
+pub enum RequestResponseRef<'a> {
+    /// A batch of requests
+    Request(&'a [Request]),
+    /// A batch of responses
+    Response(&'a [Response]),
+}
+
 /// The Ismp router dictates how messsages are routed to [`IsmpModules`]
 pub trait IsmpRouter {
     /// Get module handler by id
@@ -277,9 +365,9 @@ pub trait IsmpRouter {
 pub struct DispatchPost {
     /// The destination state machine of this request.
     pub dest: StateMachine,
-    /// Module Id of the sending module
+    /// Module Id of the sending module, may only be empty if the host allows anonymous dispatch.
     pub from: Vec<u8>,
-    /// Module ID of the receiving module
+    /// Module ID of the receiving module, this is mandatory.
     pub to: Vec<u8>,
     /// Timestamp which this request expires in seconds.
     pub timeout_timestamp: u64,
@@ -288,6 +376,14 @@ pub struct DispatchPost {
     /// Gas limit for executing request on destination chain
     /// This should be zero if the destination module is not a contract
     pub gas_limit: u64,
+    /// Optional soft delivery deadline in seconds.
+    pub deliver_by: Option<u64>,
+    /// Module ID that should receive the response, defaults to the sending module.
+    pub response_to: Option<Vec<u8>>,
+    /// Opaque application metadata, at most [`MAX_POST_META_LEN`] bytes.
+    pub meta: Option<Vec<u8>>,
+    /// Whether a response to this request is expected.
+    pub expects_response: bool,
 }
 
 /// Simplified GET request, intended to be used for sending outgoing requests
@@ -295,11 +391,12 @@ pub struct DispatchPost {
 pub struct DispatchGet {
     /// The destination state machine of this request.
     pub dest: StateMachine,
-    /// Module Id of the sending module
+    /// Module Id of the sending module, may only be empty if the host allows anonymous dispatch.
     pub from: Vec<u8>,
     /// Raw Storage keys that would be used to fetch the values from the counterparty
     pub keys: Vec<Vec<u8>>,
-    /// Height at which to read the state machine.
+    /// Height at which to read the state machine. Responses are only accepted once the state
+    /// commitment at this height has passed its challenge period and is not frozen.
     pub height: u64,
     /// Host timestamp at which this request expires in seconds
     pub timeout_timestamp: u64,
@@ -317,12 +414,71 @@ pub enum DispatchRequest {
     Get(DispatchGet),
 }
 
+/// Validate an outgoing request before it is assigned a nonce and committed. Returns
+/// [`Error::SelfDispatch`] if the destination is the host state machine and
+/// [`Error::UnsupportedStateMachine`] if the destination is not supported by the host.
+/// Returns [`Error::TooManyKeys`] if a GET request reads more keys than
+/// [`IsmpHost::max_get_keys`] allows, with a zero nonce as none has been assigned yet.
+/// Returns [`Error::EmptyModuleId`] if a POST request has an empty `to`, or if the request has an
+/// empty `from` and [`IsmpHost::allows_anonymous_dispatch`] is false, an empty `data` is valid.
+/// Returns [`Error::MetadataTooLarge`] if the metadata of a POST request exceeds
+/// [`MAX_POST_META_LEN`].
+/// Returns [`Error::Unauthorized`] if the module identified by the request's `from` field doesn't
+/// authorize the `caller` through [`crate::module::IsmpModule::is_authorized_source`]. The
+/// `caller` must be authenticated by the dispatcher, it can't be taken from the request. Anonymous
+/// requests skip this check.
+pub fn validate_dispatch<H: IsmpHost + ?Sized>(
+    host: &H,
+    caller: &[u8],
+    request: &DispatchRequest,
+) -> Result<(), Error> {
+    let (dest, from) = match request {
+        DispatchRequest::Get(get) => (get.dest, &get.from),
+        DispatchRequest::Post(post) => (post.dest, &post.from),
+    };
+    if dest == host.host_state_machine() {
+        Err(Error::SelfDispatch { state_machine: dest })?
+    }
+    if !host.is_supported_state_machine(&dest) {
+        Err(Error::UnsupportedStateMachine { state_machine: dest })?
+    }
+    match request {
+        DispatchRequest::Get(get) => {
+            if let Some(max) = host.max_get_keys().filter(|max| get.keys.len() > *max) {
+                Err(Error::TooManyKeys { nonce: 0, keys: get.keys.len(), max })?
+            }
+        }
+        DispatchRequest::Post(post) => {
+            if post.to.is_empty() {
+                Err(Error::EmptyModuleId { field: "to".to_string() })?
+            }
+            if let Some(meta) = post.meta.as_ref().filter(|meta| meta.len() > MAX_POST_META_LEN) {
+                Err(Error::MetadataTooLarge { len: meta.len(), max: MAX_POST_META_LEN })?
+            }
+        }
+    }
+    if from.is_empty() {
+        if !host.allows_anonymous_dispatch() {
+            Err(Error::EmptyModuleId { field: "from".to_string() })?
+        }
+        // Anonymous requests have no module to authorize the caller
+        return Ok(())
+    }
+    if !host.ismp_router().module_for_id(from.clone())?.is_authorized_source(caller, from) {
+        Err(Error::Unauthorized { account: caller.to_vec() })?
+    }
+    Ok(())
+}
+
 /// The Ismp dispatcher allows [`IsmpModules`] to send out outgoing [`Request`] or [`Response`]
 /// [`Event`] should be emitted after successful dispatch
 pub trait IsmpDispatcher {
-    /// Dispatches an outgoing request, the dispatcher should commit them to host state trie
-    fn dispatch_request(&self, request: DispatchRequest) -> Result<(), Error>;
+    /// Dispatches an outgoing request, the dispatcher should commit them to host state trie.
+    /// `caller` is the identity of the module sending the request, as authenticated by the host.
+    /// Should reject requests that fail [`validate_dispatch`] with the error it returns.
+    fn dispatch_request(&self, request: DispatchRequest, caller: &[u8]) -> Result<(), Error>;
 
-    /// Dispatches an outgoing response, the dispatcher should commit them to host state trie
+    /// Dispatches an outgoing response, the dispatcher should commit them to host state trie.
+    /// Should return [`Error::UnexpectedResponse`] if the request does not expect a response.
     fn dispatch_response(&self, response: PostResponse) -> Result<(), Error>;
 }
@@ -0,0 +1,63 @@
+// Copyright (C) Polytope Labs Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of Merkle-Patricia state proofs for Substrate based state machines, for consensus
+//! clients to delegate [`StateMachineClient::verify_state_proof`] to.
+//!
+//! [`StateMachineClient::verify_state_proof`]: crate::consensus::StateMachineClient::verify_state_proof
+
+use crate::{consensus::StateProofResult, error::Error};
+use alloc::{format, vec::Vec};
+use hash256_std_hasher::Hash256StdHasher;
+use primitive_types::H256;
+use sp_trie::{LayoutV1, StorageProof};
+
+/// The blake2-256 hasher used by Substrate state tries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Blake2Hasher;
+
+impl hash_db::Hasher for Blake2Hasher {
+    type Out = H256;
+    type StdHasher = Hash256StdHasher;
+    const LENGTH: usize = 32;
+
+    fn hash(x: &[u8]) -> H256 {
+        sp_core::hashing::blake2_256(x).into()
+    }
+}
+
+/// The layout of Substrate state tries. Reading a trie is the same under both state versions, so
+/// this also verifies proofs of tries written with the original layout.
+pub type SubstrateLayout = LayoutV1<Blake2Hasher>;
+
+/// Verify a proof of the given keys against the state root of a Substrate state machine, where the
+/// proof is the set of encoded trie nodes as returned by `state_getReadProof`. Returns the value of
+/// every key, a `None` value is a verified proof that the key is absent. Returns
+/// [`Error::MembershipProofVerificationFailed`] if the proof is missing any node needed to read a
+/// key.
+pub fn verify_substrate_state_proof(
+    root: H256,
+    keys: &[Vec<u8>],
+    proof: &[Vec<u8>],
+) -> Result<StateProofResult, Error> {
+    let db = StorageProof::new(proof.iter().cloned()).into_memory_db::<Blake2Hasher>();
+    keys.iter()
+        .map(|key| {
+            let value = sp_trie::read_trie_value::<SubstrateLayout, _>(&db, &root, key, None, None)
+                .map_err(|e| Error::MembershipProofVerificationFailed(format!("{e:?}")))?;
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
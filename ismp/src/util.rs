@@ -1,7 +1,12 @@
 //! ISMP utilities
 
-use crate::router::{Request, Response};
-use alloc::{string::ToString, vec::Vec};
+use crate::{
+    host::IsmpHost,
+    router::{Request, Response},
+};
+use alloc::{collections::BTreeSet, string::ToString, vec, vec::Vec};
+use codec::Encode;
+use core::{ops::RangeInclusive, time::Duration};
 use primitive_types::H256;
 
 /// A trait that returns a 256 bit keccak has of some bytes
@@ -12,11 +17,37 @@ pub trait Keccak256 {
         Self: Sized;
 }
 
+/// A [`Keccak256`] implementation backed by the `sha3` crate, for tooling that has no host to hash
+/// with.
+#[cfg(feature = "keccak")]
+pub struct BuiltinKeccak;
+
+#[cfg(feature = "keccak")]
+impl Keccak256 for BuiltinKeccak {
+    fn keccak256(bytes: &[u8]) -> H256 {
+        use sha3::Digest;
+        H256(sha3::Keccak256::digest(bytes).into())
+    }
+}
+
+/// Returns the commitment of a request using the built-in keccak implementation. Hosts with a
+/// custom [`Keccak256`] implementation should use [`hash_request`] instead.
+#[cfg(feature = "keccak")]
+pub fn hash_request_keccak(req: &Request) -> H256 {
+    hash_request::<BuiltinKeccak>(req)
+}
+
+/// Returns the current time as reported by the host. This is the only way handlers should read
+/// the current time, the crate is `no_std` and must never reach for `std::time`.
+pub fn now_from_host<H: IsmpHost + ?Sized>(host: &H) -> Duration {
+    host.timestamp()
+}
+
 /// Return the keccak256 hash of a request
 pub fn hash_request<H: Keccak256>(req: &Request) -> H256 {
     match req {
         Request::Post(post) => {
-            let mut buf = Vec::new();
+            let mut buf = vec![post.version];
 
             let source_chain = post.source.to_string();
             let dest_chain = post.dest.to_string();
@@ -30,6 +61,11 @@ pub fn hash_request<H: Keccak256>(req: &Request) -> H256 {
             buf.extend_from_slice(&post.to);
             buf.extend_from_slice(&post.data);
             buf.extend_from_slice(&post.gas_limit.to_be_bytes());
+            // The optional fields are appended as a single scale encoded tuple, whose option tags
+            // and length prefixes keep each field apart from its neighbours.
+            let extensions =
+                (post.deliver_by, &post.response_to, &post.meta, post.expects_response).encode();
+            buf.extend_from_slice(&extensions);
             H::keccak256(&buf[..])
         }
         Request::Get(get) => {
@@ -53,6 +89,30 @@ pub fn hash_request<H: Keccak256>(req: &Request) -> H256 {
     }
 }
 
+/// The prefix under which request receipts are stored in the canonical storage layout
+pub const REQUEST_RECEIPTS_PREFIX: &[u8] = b"ismp/request_receipts/";
+
+/// Return the storage key of the receipt for a request with the given commitment, as written by
+/// a destination host using the canonical storage layout. Relayers use this key to prove the
+/// delivery or non-delivery of a request.
+pub fn request_receipt_key(commitment: H256) -> Vec<u8> {
+    let mut key = REQUEST_RECEIPTS_PREFIX.to_vec();
+    key.extend_from_slice(commitment.as_bytes());
+    key
+}
+
+/// Compact a set of nonces into sorted, non-overlapping ranges of consecutive nonces
+pub fn compact_nonces(nonces: impl IntoIterator<Item = u64>) -> Vec<RangeInclusive<u64>> {
+    let mut ranges: Vec<RangeInclusive<u64>> = Vec::new();
+    for nonce in nonces.into_iter().collect::<BTreeSet<_>>() {
+        match ranges.last_mut() {
+            Some(range) if *range.end() + 1 == nonce => *range = *range.start()..=nonce,
+            _ => ranges.push(nonce..=nonce),
+        }
+    }
+    ranges
+}
+
 /// Return the keccak256 of a response
 pub fn hash_response<H: Keccak256>(res: &Response) -> H256 {
     let (req, response) = match res {
@@ -0,0 +1,81 @@
+// Copyright (C) Polytope Labs Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Side-effect free proof verification for read-only integrations.
+//!
+//! These functions perform the same verification as the message handlers, but never dispatch to
+//! the router and never mutate host state, so observers such as indexers can reuse the canonical
+//! verification logic safely.
+
+use crate::{
+    consensus::StateProof,
+    error::Error,
+    handlers::{
+        check_get_keys, check_proof_values, validate_request_message, validate_state_machine,
+        ClientCache,
+    },
+    host::IsmpHost,
+    messaging::{sufficient_proof_height, RequestMessage, ResponseMessage},
+    router::{GetResponse, Request, RequestResponseRef},
+};
+use alloc::{string::ToString, vec::Vec};
+
+/// Verify the membership proof of a request message and return the proven requests. Returns no
+/// requests if the host's unknown state machine policy drops the batch.
+pub fn verify_request_membership<H>(host: &H, msg: &RequestMessage) -> Result<Vec<Request>, Error>
+where
+    H: IsmpHost,
+{
+    let Some(state_machine) = validate_request_message(host, msg, &ClientCache::default())? else {
+        return Ok(Vec::new())
+    };
+    let state = host.state_machine_commitment(msg.proof.height)?;
+    let requests = msg.requests.iter().cloned().map(Request::Post).collect::<Vec<_>>();
+    state_machine.verify_membership(
+        host,
+        RequestResponseRef::Request(&requests),
+        state,
+        &msg.proof,
+    )?;
+
+    Ok(requests)
+}
+
+/// Verify the state proof of a GET response message and return the proven values for each
+/// request. Returns an error if the message is not a GET response.
+pub fn verify_get_response<H>(host: &H, msg: &ResponseMessage) -> Result<Vec<GetResponse>, Error>
+where
+    H: IsmpHost,
+{
+    let ResponseMessage::Get { requests, proof } = msg else {
+        Err(Error::ImplementationSpecific("Expected a get response message".to_string()))?
+    };
+
+    check_get_keys(host, requests)?;
+    let state_machine = validate_state_machine(host, proof, &ClientCache::default())?;
+    let state = host.state_machine_commitment(proof.height)?;
+    sufficient_proof_height(requests, proof)?;
+
+    requests
+        .iter()
+        .map(|request| {
+            let get = request.get_request()?;
+            let state_proof = StateProof { keys: get.keys.clone(), height: proof.height };
+            let values = state_machine.verify_state_proof(host, state_proof, state, proof)?;
+            check_proof_values(&get.keys, &values)?;
+            Ok(GetResponse { get, values })
+        })
+        .collect()
+}
@@ -0,0 +1,44 @@
+// Copyright (C) Polytope Labs Ltd.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! ISMP protocol versioning
+
+/// The protocol version implemented by this crate. The high byte is the major version and the
+/// low byte is the minor version, hosts can only exchange messages within the same major version.
+pub const ISMP_PROTOCOL_VERSION: u16 = 0x0100;
+
+/// Returns the major component of a protocol version
+pub fn major(version: u16) -> u8 {
+    (version >> 8) as u8
+}
+
+/// Returns true if a message declaring the given protocol version can be handled by this crate.
+/// Versions are compatible if they share the same major version.
+pub fn is_compatible(version: u16) -> bool {
+    major(version) == major(ISMP_PROTOCOL_VERSION)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_compatible_within_a_major_version() {
+        assert!(is_compatible(ISMP_PROTOCOL_VERSION));
+        assert!(is_compatible(ISMP_PROTOCOL_VERSION + 1));
+        assert!(!is_compatible(ISMP_PROTOCOL_VERSION + 0x0100));
+        assert!(!is_compatible(ISMP_PROTOCOL_VERSION - 0x0100));
+    }
+}